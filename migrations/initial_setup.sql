@@ -4,6 +4,7 @@ CREATE EXTENSION IF NOT EXISTS "uuid-ossp";
 -- Define Enums
 CREATE TYPE idempotency_status AS ENUM ('pending', 'success', 'failed');
 CREATE TYPE webhook_event_status AS ENUM ('pending', 'delivered', 'failed');
+CREATE TYPE user_role AS ENUM ('owner', 'admin', 'developer', 'viewer');
 
 CREATE TABLE IF NOT EXISTS businesses (
     id              UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
@@ -14,6 +15,39 @@ CREATE TABLE IF NOT EXISTS businesses (
 );
 
 
+-- Individual logins within a business. The business's signup credentials
+-- become its owner user.
+CREATE TABLE IF NOT EXISTS users (
+    id              UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    business_id     UUID NOT NULL REFERENCES businesses(id) ON DELETE CASCADE,
+    email           TEXT UNIQUE NOT NULL,
+    password_hash   TEXT NOT NULL,
+    name            TEXT,
+    role            user_role NOT NULL,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_users_business_id ON users(business_id);
+
+INSERT INTO users (business_id, email, password_hash, name, role)
+SELECT id, email, password_hash, name, 'owner' FROM businesses
+ON CONFLICT (email) DO NOTHING;
+
+CREATE TABLE IF NOT EXISTS user_invitations (
+    id              UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    business_id     UUID NOT NULL REFERENCES businesses(id) ON DELETE CASCADE,
+    email           TEXT NOT NULL,
+    role            user_role NOT NULL,
+    token_hash      TEXT UNIQUE NOT NULL,
+    invited_by      UUID REFERENCES users(id) ON DELETE SET NULL,
+    expires_at      TIMESTAMP NOT NULL,
+    accepted_at     TIMESTAMP,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_invitations_business_id ON user_invitations(business_id);
+
+
 CREATE TABLE IF NOT EXISTS api_keys (
     id              UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
     business_id     UUID NOT NULL REFERENCES businesses(id) ON DELETE CASCADE,
@@ -22,6 +56,10 @@ CREATE TABLE IF NOT EXISTS api_keys (
     created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP
 );
 
+-- Keys carry the role of the user who minted them; keys predating users act as owner
+ALTER TABLE api_keys ADD COLUMN IF NOT EXISTS role user_role NOT NULL DEFAULT 'owner';
+ALTER TABLE api_keys ADD COLUMN IF NOT EXISTS created_by UUID REFERENCES users(id) ON DELETE SET NULL;
+
 CREATE INDEX IF NOT EXISTS idx_api_keys_business_id ON api_keys(business_id);
 CREATE INDEX IF NOT EXISTS idx_api_keys_key_hash ON api_keys(key_hash);
 
@@ -43,8 +81,8 @@ CREATE TABLE IF NOT EXISTS transactions (
     from_account_id     UUID REFERENCES accounts(id) ON DELETE SET NULL,
     to_account_id       UUID REFERENCES accounts(id) ON DELETE SET NULL,
     amount              BIGINT NOT NULL,
-    type                TEXT NOT NULL, -- credit | debit | transfer
-    status              TEXT NOT NULL, -- success | failed
+    type                TEXT NOT NULL, -- credit | debit | transfer | reversal | multi_leg
+    status              TEXT NOT NULL, -- success | pending | settled | failed
     idempotency_key     TEXT,
     created_at          TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
     
@@ -93,3 +131,346 @@ CREATE TABLE IF NOT EXISTS webhook_events (
 
 CREATE INDEX IF NOT EXISTS idx_webhook_events_webhook_endpoint_id ON webhook_events(webhook_endpoint_id);
 CREATE INDEX IF NOT EXISTS idx_webhook_events_status ON webhook_events(status);
+
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    id              UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    business_id     UUID NOT NULL REFERENCES businesses(id) ON DELETE CASCADE,
+    family_id       UUID NOT NULL, -- all tokens rotated from the same login
+    token_hash      TEXT UNIQUE NOT NULL,
+    expires_at      TIMESTAMP NOT NULL,
+    revoked_at      TIMESTAMP,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_refresh_tokens_family_id ON refresh_tokens(family_id);
+
+-- Sessions belong to a user. Tokens issued before users existed were for the
+-- business's signup login, which became its owner user.
+ALTER TABLE refresh_tokens ADD COLUMN IF NOT EXISTS user_id UUID REFERENCES users(id) ON DELETE CASCADE;
+UPDATE refresh_tokens r SET user_id = u.id
+FROM businesses b JOIN users u ON u.business_id = b.id AND u.email = b.email
+WHERE r.user_id IS NULL AND b.id = r.business_id;
+DELETE FROM refresh_tokens WHERE user_id IS NULL;
+ALTER TABLE refresh_tokens ALTER COLUMN user_id SET NOT NULL;
+
+-- Failed login tracking for lockout, keyed by the email that was attempted
+CREATE TABLE IF NOT EXISTS login_attempts (
+    email           TEXT PRIMARY KEY,
+    failed_count    INT NOT NULL DEFAULT 0,
+    last_failed_at  TIMESTAMP,
+    locked_until    TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS password_reset_tokens (
+    id              UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id         UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash      TEXT UNIQUE NOT NULL,
+    expires_at      TIMESTAMP NOT NULL,
+    used_at         TIMESTAMP,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_password_reset_tokens_user_id ON password_reset_tokens(user_id);
+
+-- TOTP two-factor authentication (RFC 6238)
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_secret TEXT; -- base32, set during enrollment
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_enabled BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_last_step BIGINT; -- last accepted time step, blocks replay
+
+CREATE TABLE IF NOT EXISTS totp_recovery_codes (
+    id              UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id         UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    code_hash       TEXT NOT NULL,
+    used_at         TIMESTAMP,
+    created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_totp_recovery_codes_user_id ON totp_recovery_codes(user_id);
+
+-- Emails are stored trimmed and lowercased, and unique regardless of case.
+-- Rows whose normalized email would collide with another row are left untouched,
+-- so the unique indexes below fail until those duplicates are merged by hand:
+--   SELECT LOWER(TRIM(email)), COUNT(*) FROM businesses GROUP BY 1 HAVING COUNT(*) > 1;
+UPDATE businesses b SET email = LOWER(TRIM(b.email))
+WHERE b.email <> LOWER(TRIM(b.email))
+  AND NOT EXISTS (
+      SELECT 1 FROM businesses o WHERE o.id <> b.id AND LOWER(TRIM(o.email)) = LOWER(TRIM(b.email))
+  );
+
+UPDATE users u SET email = LOWER(TRIM(u.email))
+WHERE u.email <> LOWER(TRIM(u.email))
+  AND NOT EXISTS (
+      SELECT 1 FROM users o WHERE o.id <> u.id AND LOWER(TRIM(o.email)) = LOWER(TRIM(u.email))
+  );
+
+UPDATE user_invitations SET email = LOWER(TRIM(email)) WHERE email <> LOWER(TRIM(email));
+DELETE FROM login_attempts WHERE email <> LOWER(TRIM(email));
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_businesses_email_lower ON businesses(LOWER(email));
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_email_lower ON users(LOWER(email));
+
+-- X-Request-Id of the API call that produced the row, for support correlation
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS request_id TEXT;
+ALTER TABLE webhook_events ADD COLUMN IF NOT EXISTS request_id TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_transactions_request_id ON transactions(request_id);
+
+-- Keyset pagination on GET /accounts orders by (created_at, id)
+UPDATE accounts SET created_at = CURRENT_TIMESTAMP WHERE created_at IS NULL;
+ALTER TABLE accounts ALTER COLUMN created_at SET NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_accounts_created_at_id ON accounts(created_at, id);
+
+ALTER TABLE accounts ADD COLUMN IF NOT EXISTS status TEXT NOT NULL DEFAULT 'active'; -- active | frozen | closed
+
+-- Keyset pagination on GET /transactions orders by (created_at, id)
+UPDATE transactions SET created_at = CURRENT_TIMESTAMP WHERE created_at IS NULL;
+ALTER TABLE transactions ALTER COLUMN created_at SET NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_transactions_business_created_at ON transactions(business_id, created_at, id);
+
+-- Double-entry ledger. Every money movement is a journal entry whose postings
+-- debit and credit equal amounts. A posting hits either a customer account or a
+-- per-currency system account standing for money outside the platform.
+-- accounts.balance is a cache of the account's postings (credits - debits).
+CREATE TABLE IF NOT EXISTS journal_entries (
+    id                  UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    transaction_id      UUID REFERENCES transactions(id),
+    kind                TEXT NOT NULL, -- transfer | credit | debit | reversal | multi_leg | opening_balance
+    currency            TEXT NOT NULL,
+    created_at          TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_journal_entries_transaction_id ON journal_entries(transaction_id);
+
+CREATE TABLE IF NOT EXISTS ledger_entries (
+    id                  UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    journal_entry_id    UUID NOT NULL REFERENCES journal_entries(id),
+    account_id          UUID REFERENCES accounts(id),
+    system_account      TEXT, -- external_funding | external_payouts | opening_balances
+    direction           TEXT NOT NULL CHECK (direction IN ('debit', 'credit')),
+    amount              BIGINT NOT NULL CHECK (amount > 0),
+    created_at          TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    CHECK (num_nonnulls(account_id, system_account) = 1)
+);
+
+CREATE INDEX IF NOT EXISTS idx_ledger_entries_journal_entry_id ON ledger_entries(journal_entry_id);
+CREATE INDEX IF NOT EXISTS idx_ledger_entries_account_created_at ON ledger_entries(account_id, created_at);
+
+-- The ledger is append-only; corrections are new, offsetting entries
+CREATE OR REPLACE FUNCTION ledger_reject_change() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION '% is append-only', TG_TABLE_NAME;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS journal_entries_append_only ON journal_entries;
+CREATE TRIGGER journal_entries_append_only BEFORE UPDATE OR DELETE ON journal_entries
+    FOR EACH ROW EXECUTE FUNCTION ledger_reject_change();
+DROP TRIGGER IF EXISTS journal_entries_no_truncate ON journal_entries;
+CREATE TRIGGER journal_entries_no_truncate BEFORE TRUNCATE ON journal_entries
+    FOR EACH STATEMENT EXECUTE FUNCTION ledger_reject_change();
+DROP TRIGGER IF EXISTS ledger_entries_append_only ON ledger_entries;
+CREATE TRIGGER ledger_entries_append_only BEFORE UPDATE OR DELETE ON ledger_entries
+    FOR EACH ROW EXECUTE FUNCTION ledger_reject_change();
+DROP TRIGGER IF EXISTS ledger_entries_no_truncate ON ledger_entries;
+CREATE TRIGGER ledger_entries_no_truncate BEFORE TRUNCATE ON ledger_entries
+    FOR EACH STATEMENT EXECUTE FUNCTION ledger_reject_change();
+
+-- Checked at commit, once every posting of the entry has been written
+CREATE OR REPLACE FUNCTION ledger_check_balanced() RETURNS trigger AS $$
+BEGIN
+    IF (SELECT SUM(CASE direction WHEN 'debit' THEN amount ELSE -amount END)
+        FROM ledger_entries WHERE journal_entry_id = NEW.journal_entry_id) <> 0 THEN
+        RAISE EXCEPTION 'journal entry % is unbalanced', NEW.journal_entry_id;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS ledger_entries_balanced ON ledger_entries;
+CREATE CONSTRAINT TRIGGER ledger_entries_balanced AFTER INSERT ON ledger_entries
+    DEFERRABLE INITIALLY DEFERRED
+    FOR EACH ROW EXECUTE FUNCTION ledger_check_balanced();
+
+-- Backfill: accounts without postings open with whatever their balance was
+-- before the recorded transactions, then every successful transaction is posted.
+WITH opening AS (
+    SELECT a.id, a.currency, a.created_at, uuid_generate_v4() AS journal_id,
+           a.balance - COALESCE((
+               SELECT SUM(CASE WHEN t.to_account_id = a.id THEN t.amount ELSE 0 END)
+                    - SUM(CASE WHEN t.from_account_id = a.id THEN t.amount ELSE 0 END)
+               FROM transactions t
+               WHERE t.status = 'success' AND (t.to_account_id = a.id OR t.from_account_id = a.id)
+           ), 0) AS amount
+    FROM accounts a
+    WHERE NOT EXISTS (SELECT 1 FROM ledger_entries l WHERE l.account_id = a.id)
+),
+journals AS (
+    INSERT INTO journal_entries (id, kind, currency, created_at)
+    SELECT journal_id, 'opening_balance', currency, created_at FROM opening WHERE amount <> 0
+)
+INSERT INTO ledger_entries (journal_entry_id, account_id, system_account, direction, amount, created_at)
+SELECT journal_id, NULL, 'opening_balances', CASE WHEN amount > 0 THEN 'debit' ELSE 'credit' END, ABS(amount), created_at
+FROM opening WHERE amount <> 0
+UNION ALL
+SELECT journal_id, id, NULL, CASE WHEN amount > 0 THEN 'credit' ELSE 'debit' END, ABS(amount), created_at
+FROM opening WHERE amount <> 0;
+
+WITH moved AS (
+    SELECT t.id, t.type, t.amount, t.from_account_id, t.to_account_id, t.created_at,
+           COALESCE(fa.currency, ta.currency) AS currency, uuid_generate_v4() AS journal_id
+    FROM transactions t
+    LEFT JOIN accounts fa ON fa.id = t.from_account_id
+    LEFT JOIN accounts ta ON ta.id = t.to_account_id
+    WHERE t.status = 'success'
+      AND COALESCE(fa.currency, ta.currency) IS NOT NULL
+      AND NOT EXISTS (SELECT 1 FROM journal_entries j WHERE j.transaction_id = t.id)
+),
+journals AS (
+    INSERT INTO journal_entries (id, transaction_id, kind, currency, created_at)
+    SELECT journal_id, id, type, currency, created_at FROM moved
+)
+INSERT INTO ledger_entries (journal_entry_id, account_id, system_account, direction, amount, created_at)
+SELECT journal_id, from_account_id, CASE WHEN from_account_id IS NULL THEN 'external_funding' END, 'debit', amount, created_at
+FROM moved
+UNION ALL
+SELECT journal_id, to_account_id, CASE WHEN to_account_id IS NULL THEN 'external_payouts' END, 'credit', amount, created_at
+FROM moved;
+
+-- Reversals (type 'reversal') point at the transfer they return funds from
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS parent_transaction_id UUID REFERENCES transactions(id);
+CREATE INDEX IF NOT EXISTS idx_transactions_parent_transaction_id ON transactions(parent_transaction_id)
+    WHERE parent_transaction_id IS NOT NULL;
+
+-- Authorization holds reserve funds on an account until captured, voided or
+-- expired. Active, unexpired holds reduce the available balance, not the balance.
+CREATE TABLE IF NOT EXISTS holds (
+    id                  UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    business_id         UUID NOT NULL REFERENCES businesses(id) ON DELETE CASCADE,
+    account_id          UUID NOT NULL REFERENCES accounts(id),
+    amount              BIGINT NOT NULL CHECK (amount > 0),
+    status              TEXT NOT NULL DEFAULT 'active', -- active | captured | voided | expired
+    captured_amount     BIGINT,
+    transaction_id      UUID REFERENCES transactions(id),
+    expires_at          TIMESTAMP NOT NULL,
+    created_at          TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at          TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_holds_active_account_id ON holds(account_id) WHERE status = 'active';
+CREATE INDEX IF NOT EXISTS idx_holds_active_expires_at ON holds(expires_at) WHERE status = 'active';
+
+-- Pending transfers hold funds on their source account until settled or failed.
+-- Every status change of a pending transfer is recorded here.
+CREATE INDEX IF NOT EXISTS idx_transactions_pending_from_account_id ON transactions(from_account_id)
+    WHERE status = 'pending';
+
+CREATE TABLE IF NOT EXISTS transaction_status_changes (
+    id                  UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    transaction_id      UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    from_status         TEXT, -- NULL when the transaction was created
+    to_status           TEXT NOT NULL,
+    request_id          TEXT,
+    created_at          TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_transaction_status_changes_transaction_id
+    ON transaction_status_changes(transaction_id, created_at);
+
+-- Batch transfers. Items run as ordinary transfers; the batch records the mode,
+-- progress and each item's outcome. Large batches are queued for a worker.
+CREATE TABLE IF NOT EXISTS transfer_batches (
+    id                  UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    business_id         UUID NOT NULL REFERENCES businesses(id) ON DELETE CASCADE,
+    idempotency_key     TEXT NOT NULL,
+    mode                TEXT NOT NULL, -- all_or_nothing | best_effort
+    status              TEXT NOT NULL DEFAULT 'queued', -- queued | processing | completed | failed
+    request_id          TEXT,
+    claimed_at          TIMESTAMP,
+    created_at          TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    completed_at        TIMESTAMP,
+
+    UNIQUE (business_id, idempotency_key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_transfer_batches_unfinished ON transfer_batches(created_at)
+    WHERE status IN ('queued', 'processing');
+
+CREATE TABLE IF NOT EXISTS transfer_batch_items (
+    batch_id            UUID NOT NULL REFERENCES transfer_batches(id) ON DELETE CASCADE,
+    position            INTEGER NOT NULL,
+    from_account_id     UUID NOT NULL,
+    to_account_id       UUID NOT NULL,
+    amount              BIGINT NOT NULL CHECK (amount > 0),
+    status              TEXT NOT NULL DEFAULT 'queued', -- queued | succeeded | failed | skipped
+    transaction_id      UUID REFERENCES transactions(id),
+    error_code          TEXT,
+    error_message       TEXT,
+
+    PRIMARY KEY (batch_id, position)
+);
+
+-- Scheduled and recurring transfers. Occurrence n of a schedule runs at
+-- start_at + (n - 1) intervals, as a transfer with idempotency key
+-- '{schedule id}/{n}', in the same database transaction that records the run.
+CREATE TABLE IF NOT EXISTS scheduled_transfers (
+    id                  UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    business_id         UUID NOT NULL REFERENCES businesses(id) ON DELETE CASCADE,
+    from_account_id     UUID NOT NULL REFERENCES accounts(id),
+    to_account_id       UUID NOT NULL REFERENCES accounts(id),
+    amount              BIGINT NOT NULL CHECK (amount > 0),
+    start_at            TIMESTAMP NOT NULL,
+    interval            TEXT, -- daily | weekly | monthly; NULL runs once
+    end_at              TIMESTAMP,
+    max_occurrences     INTEGER,
+    occurrences         INTEGER NOT NULL DEFAULT 0,
+    next_run_at         TIMESTAMP,
+    status              TEXT NOT NULL DEFAULT 'active', -- active | completed | cancelled
+    idempotency_key     TEXT NOT NULL,
+    created_at          TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at          TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    UNIQUE (business_id, idempotency_key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_scheduled_transfers_due ON scheduled_transfers(next_run_at)
+    WHERE status = 'active';
+CREATE INDEX IF NOT EXISTS idx_scheduled_transfers_business_created_at
+    ON scheduled_transfers(business_id, created_at, id);
+
+CREATE TABLE IF NOT EXISTS scheduled_transfer_runs (
+    scheduled_transfer_id   UUID NOT NULL REFERENCES scheduled_transfers(id) ON DELETE CASCADE,
+    occurrence              INTEGER NOT NULL,
+    scheduled_for           TIMESTAMP NOT NULL,
+    status                  TEXT NOT NULL, -- succeeded | failed
+    transaction_id          UUID REFERENCES transactions(id),
+    error_code              TEXT,
+    error_message           TEXT,
+    created_at              TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    PRIMARY KEY (scheduled_transfer_id, occurrence)
+);
+
+-- Legs of a multi-leg transfer (type 'multi_leg'), which has no single
+-- source or destination. Negative amounts leave an account, positive ones
+-- arrive; the legs of a transfer sum to zero.
+CREATE TABLE IF NOT EXISTS transaction_legs (
+    transaction_id  UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    position        INTEGER NOT NULL,
+    account_id      UUID REFERENCES accounts(id) ON DELETE SET NULL,
+    amount          BIGINT NOT NULL CHECK (amount <> 0),
+
+    PRIMARY KEY (transaction_id, position)
+);
+
+CREATE INDEX IF NOT EXISTS idx_transaction_legs_account_id ON transaction_legs(account_id);
+
+-- Schema version checked by GET /readyz against SCHEMA_VERSION in src/handlers/health.rs.
+-- Keep this block last: add new migrations above it and bump both numbers.
+CREATE TABLE IF NOT EXISTS schema_version (
+    id      BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    version INTEGER NOT NULL
+);
+
+INSERT INTO schema_version (version) VALUES (11)
+ON CONFLICT (id) DO UPDATE SET version = EXCLUDED.version;
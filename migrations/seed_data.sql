@@ -24,6 +24,12 @@ INSERT INTO businesses (id, name, email, password_hash) VALUES
 ('22222222-2222-2222-2222-222222222222', 'Test Business 2', 'test_business_2@example.com', '$2b$12$ciACRkyJ1hi59MQXmbrrLeol8sslAg7oW.99xzHKXWkcsIXekx8Qu')
 ON CONFLICT (email) DO UPDATE SET password_hash = EXCLUDED.password_hash;
 
+-- Owner users for both businesses (same credentials as above)
+INSERT INTO users (business_id, email, password_hash, name, role) VALUES
+('11111111-1111-1111-1111-111111111111', 'test_business_1@example.com', '$2b$12$ciACRkyJ1hi59MQXmbrrLeol8sslAg7oW.99xzHKXWkcsIXekx8Qu', 'Test Business 1', 'owner'),
+('22222222-2222-2222-2222-222222222222', 'test_business_2@example.com', '$2b$12$ciACRkyJ1hi59MQXmbrrLeol8sslAg7oW.99xzHKXWkcsIXekx8Qu', 'Test Business 2', 'owner')
+ON CONFLICT (email) DO UPDATE SET password_hash = EXCLUDED.password_hash;
+
 -- API Key for Business 1: sk_live_test_business_1_key_12345678901234567890123456789012
 -- Hash: SHA256 of "sk_live_test_business_1_key_12345678901234567890123456789012"
 INSERT INTO api_keys (business_id, key_hash, is_active) VALUES
@@ -46,3 +52,20 @@ INSERT INTO accounts (business_id, currency, balance) VALUES
 ('22222222-2222-2222-2222-222222222222', 'USD', 2000000),
 ('22222222-2222-2222-2222-222222222222', 'GBP', 750000);
 
+
+-- Opening balances for the seeded accounts, posted against the opening_balances
+-- system account so the ledger agrees with accounts.balance
+WITH opening AS (
+    SELECT a.id, a.currency, a.balance, a.created_at, uuid_generate_v4() AS journal_id
+    FROM accounts a
+    WHERE a.balance > 0
+      AND NOT EXISTS (SELECT 1 FROM ledger_entries l WHERE l.account_id = a.id)
+),
+journals AS (
+    INSERT INTO journal_entries (id, kind, currency, created_at)
+    SELECT journal_id, 'opening_balance', currency, created_at FROM opening
+)
+INSERT INTO ledger_entries (journal_entry_id, account_id, system_account, direction, amount, created_at)
+SELECT journal_id, NULL, 'opening_balances', 'debit', balance, created_at FROM opening
+UNION ALL
+SELECT journal_id, id, NULL, 'credit', balance, created_at FROM opening;
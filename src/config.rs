@@ -0,0 +1,135 @@
+use crate::telemetry::{LogFormat, TraceExporter};
+use rand::Rng;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Shortest `JWT_SECRET` accepted; HS256 keys shorter than the hash output are
+/// easier to brute-force.
+pub const MIN_JWT_SECRET_LEN: usize = 32;
+
+/// Smallest and largest amount, in minor units, one transaction may move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AmountLimits {
+    pub min: i64,
+    pub max: i64,
+}
+
+impl AmountLimits {
+    /// Parses comma-separated `CURRENCY=MIN-MAX` entries, e.g.
+    /// `USD=100-1000000,EUR=100-800000`.
+    pub fn parse_all(value: &str) -> Option<HashMap<String, Self>> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (currency, range) = entry.split_once('=')?;
+                let (min, max) = range.split_once('-')?;
+                let limits = Self {
+                    min: min.trim().parse().ok()?,
+                    max: max.trim().parse().ok()?,
+                };
+                (limits.min > 0 && limits.min <= limits.max)
+                    .then(|| (currency.trim().to_uppercase(), limits))
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone)]
+pub struct Config {
+    /// HMAC secret used to sign dashboard access tokens. Defaults to a random
+    /// key, so tokens only verify within the process that issued them.
+    pub jwt_secret: String,
+    /// Lifetime of a signed access token.
+    pub access_token_ttl: Duration,
+    /// Lifetime of a refresh token before it must be exchanged again.
+    pub refresh_token_ttl: Duration,
+    /// When set, notifications are appended to this file instead of logged.
+    pub notifier_file: Option<PathBuf>,
+    /// Format of stdout log lines.
+    pub log_format: LogFormat,
+    /// When set, spans are also exported through OpenTelemetry.
+    pub trace_exporter: Option<TraceExporter>,
+    /// `service.name` reported on exported spans.
+    pub service_name: String,
+    /// Bearer token for the `/admin` routes; they reject every request when unset.
+    pub admin_token: Option<String>,
+    /// When set, the ledger is verified in the background at this interval.
+    pub ledger_verify_interval: Option<Duration>,
+    /// When set, pending transfers are settled automatically once this old.
+    pub settlement_delay: Option<Duration>,
+    /// Per-currency bounds on a single transaction's amount. Currencies
+    /// without an entry are only bounded by the balance.
+    pub amount_limits: HashMap<String, AmountLimits>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            jwt_secret: hex::encode(rand::thread_rng().gen::<[u8; 32]>()),
+            access_token_ttl: Duration::from_secs(15 * 60),
+            refresh_token_ttl: Duration::from_secs(30 * 24 * 60 * 60),
+            notifier_file: None,
+            log_format: LogFormat::Json,
+            trace_exporter: None,
+            service_name: "dodo-transactions".to_string(),
+            admin_token: None,
+            ledger_verify_interval: None,
+            settlement_delay: None,
+            amount_limits: HashMap::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        Self {
+            jwt_secret: std::env::var("JWT_SECRET")
+                .ok()
+                .filter(|secret| secret.len() >= MIN_JWT_SECRET_LEN)
+                .expect("JWT_SECRET must be set to at least 32 bytes"),
+            access_token_ttl: env_secs("ACCESS_TOKEN_TTL_SECS")
+                .unwrap_or(defaults.access_token_ttl),
+            refresh_token_ttl: env_secs("REFRESH_TOKEN_TTL_SECS")
+                .unwrap_or(defaults.refresh_token_ttl),
+            notifier_file: std::env::var("NOTIFIER_FILE").ok().map(PathBuf::from),
+            log_format: std::env::var("LOG_FORMAT")
+                .ok()
+                .map(|value| LogFormat::parse(&value).expect("LOG_FORMAT must be json or pretty"))
+                .unwrap_or(defaults.log_format),
+            trace_exporter: std::env::var("OTEL_TRACES_EXPORTER")
+                .ok()
+                .filter(|value| value != "none")
+                .map(|value| {
+                    TraceExporter::parse(&value)
+                        .expect("OTEL_TRACES_EXPORTER must be otlp, stdout or none")
+                }),
+            service_name: std::env::var("OTEL_SERVICE_NAME").unwrap_or(defaults.service_name),
+            admin_token: std::env::var("ADMIN_TOKEN")
+                .ok()
+                .filter(|token| !token.is_empty()),
+            ledger_verify_interval: env_secs("LEDGER_VERIFY_INTERVAL_SECS")
+                .filter(|interval| !interval.is_zero()),
+            settlement_delay: env_secs("SETTLEMENT_DELAY_SECS"),
+            amount_limits: std::env::var("AMOUNT_LIMITS")
+                .ok()
+                .map(|value| {
+                    AmountLimits::parse_all(&value).expect(
+                        "AMOUNT_LIMITS must be CURRENCY=MIN-MAX entries, separated by commas",
+                    )
+                })
+                .unwrap_or(defaults.amount_limits),
+        }
+    }
+}
+
+fn env_secs(name: &str) -> Option<Duration> {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
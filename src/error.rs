@@ -0,0 +1,251 @@
+use axum::{
+    extract::rejection::{JsonRejection, PathRejection, QueryRejection},
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+#[derive(Debug, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// Every error the API can return. Each variant maps to one HTTP status and a
+/// stable `code` clients can match on; `message` is for humans and may change.
+#[derive(Debug)]
+pub enum AppError {
+    /// One or more request fields failed validation.
+    Validation(Vec<FieldError>),
+    /// Malformed request that is not tied to a single validated field.
+    InvalidRequest(&'static str),
+    Unauthorized,
+    InvalidCredentials,
+    TwoFactorRequired,
+    InvalidTwoFactorCode,
+    /// A refresh, reset or invitation token that is unknown, used or expired.
+    InvalidToken(&'static str),
+    AccountLocked {
+        retry_after_seconds: i64,
+    },
+    /// The caller's rate limit bucket is empty.
+    RateLimited {
+        retry_after_seconds: i64,
+    },
+    Forbidden(&'static str),
+    NotFound(&'static str),
+    AlreadyExists(&'static str),
+    /// The resource is not in a state that allows the operation.
+    Conflict(&'static str),
+    IdempotencyInProgress,
+    IdempotencyCompleted,
+    InsufficientFunds {
+        available: i64,
+        required: i64,
+    },
+    CurrencyMismatch {
+        from_currency: String,
+        to_currency: String,
+    },
+    /// The database aborted the transaction because of a deadlock or a
+    /// serialization failure. Retrying the whole operation can succeed.
+    Contention(&'static str),
+    Internal(&'static str),
+}
+
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized
+            | AppError::InvalidCredentials
+            | AppError::TwoFactorRequired
+            | AppError::InvalidTwoFactorCode
+            | AppError::InvalidToken(_) => StatusCode::UNAUTHORIZED,
+            AppError::AccountLocked { .. } | AppError::RateLimited { .. } => {
+                StatusCode::TOO_MANY_REQUESTS
+            }
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::AlreadyExists(_)
+            | AppError::Conflict(_)
+            | AppError::IdempotencyInProgress
+            | AppError::IdempotencyCompleted => StatusCode::CONFLICT,
+            AppError::InsufficientFunds { .. } | AppError::CurrencyMismatch { .. } => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            AppError::Contention(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Validation(_) => "validation_failed",
+            AppError::InvalidRequest(_) => "invalid_request",
+            AppError::Unauthorized => "unauthorized",
+            AppError::InvalidCredentials => "invalid_credentials",
+            AppError::TwoFactorRequired => "two_factor_required",
+            AppError::InvalidTwoFactorCode => "invalid_two_factor_code",
+            AppError::InvalidToken(_) => "invalid_token",
+            AppError::AccountLocked { .. } => "account_locked",
+            AppError::RateLimited { .. } => "rate_limited",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::NotFound(_) => "not_found",
+            AppError::AlreadyExists(_) => "already_exists",
+            AppError::Conflict(_) => "conflict",
+            AppError::IdempotencyInProgress => "idempotency_in_progress",
+            AppError::IdempotencyCompleted => "idempotency_completed",
+            AppError::InsufficientFunds { .. } => "insufficient_funds",
+            AppError::CurrencyMismatch { .. } => "currency_mismatch",
+            AppError::Contention(_) => "contention",
+            AppError::Internal(_) => "internal_error",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            AppError::Validation(_) => "Validation failed",
+            AppError::Unauthorized => "Unauthorized",
+            AppError::InvalidCredentials => "Invalid credentials",
+            AppError::TwoFactorRequired => "Two-factor code required",
+            AppError::InvalidTwoFactorCode => "Invalid two-factor code",
+            AppError::AccountLocked { .. } => "Too many failed login attempts",
+            AppError::RateLimited { .. } => "Too many requests",
+            AppError::IdempotencyInProgress => "Operation in progress",
+            AppError::IdempotencyCompleted => "Operation already completed successfully",
+            AppError::InsufficientFunds { .. } => "Insufficient balance",
+            AppError::CurrencyMismatch { .. } => "Currency mismatch",
+            AppError::InvalidRequest(message)
+            | AppError::InvalidToken(message)
+            | AppError::Forbidden(message)
+            | AppError::NotFound(message)
+            | AppError::AlreadyExists(message)
+            | AppError::Conflict(message)
+            | AppError::Contention(message)
+            | AppError::Internal(message) => message,
+        }
+    }
+
+    /// Maps a failed query to `Contention` when the database aborted it for a
+    /// deadlock (`40P01`) or serialization failure (`40001`), and to
+    /// `Internal` otherwise. Use as `.map_err(AppError::database("..."))`.
+    pub fn database(message: &'static str) -> impl FnOnce(sqlx::Error) -> AppError {
+        move |error| {
+            let code = error.as_database_error().and_then(|error| error.code());
+            match code.as_deref() {
+                Some("40P01" | "40001") => AppError::Contention(message),
+                _ => AppError::Internal(message),
+            }
+        }
+    }
+
+    fn details(&self) -> Option<Value> {
+        match self {
+            AppError::Validation(fields) => Some(json!({ "fields": fields })),
+            AppError::AccountLocked {
+                retry_after_seconds,
+            }
+            | AppError::RateLimited {
+                retry_after_seconds,
+            } => Some(json!({ "retry_after_seconds": retry_after_seconds })),
+            AppError::InsufficientFunds {
+                available,
+                required,
+            } => Some(json!({ "available": available, "required": required })),
+            AppError::CurrencyMismatch {
+                from_currency,
+                to_currency,
+            } => Some(json!({ "from_currency": from_currency, "to_currency": to_currency })),
+            _ => None,
+        }
+    }
+
+    /// The JSON body sent to clients:
+    /// `{"error": {"code": ..., "message": ..., "details": ...}}`.
+    pub fn body(&self) -> Value {
+        let mut error = json!({ "code": self.code(), "message": self.message() });
+        if let Some(details) = self.details() {
+            error["details"] = details;
+        }
+        json!({ "error": error })
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code(), self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        if self.status().is_server_error() {
+            tracing::error!(code = self.code(), "{}", self.message());
+        } else {
+            tracing::debug!(code = self.code(), "{}", self.message());
+        }
+
+        let mut response = (self.status(), Json(self.body())).into_response();
+
+        if let AppError::AccountLocked {
+            retry_after_seconds,
+        }
+        | AppError::RateLimited {
+            retry_after_seconds,
+        } = self
+        {
+            if let Ok(value) = HeaderValue::from_str(&retry_after_seconds.to_string()) {
+                response.headers_mut().insert(header::RETRY_AFTER, value);
+            }
+        }
+
+        response
+    }
+}
+
+// Extractor rejections, so malformed requests get the same JSON error body as
+// every other failure. Handlers extract through `crate::extract`.
+
+impl From<JsonRejection> for AppError {
+    fn from(rejection: JsonRejection) -> Self {
+        match rejection {
+            JsonRejection::JsonDataError(error) => AppError::Validation(vec![FieldError {
+                field: "body".to_string(),
+                message: error.body_text(),
+            }]),
+            JsonRejection::JsonSyntaxError(_) => {
+                AppError::InvalidRequest("Request body is not valid JSON")
+            }
+            JsonRejection::MissingJsonContentType(_) => {
+                AppError::InvalidRequest("Expected request with `Content-Type: application/json`")
+            }
+            _ => AppError::InvalidRequest("Failed to read request body"),
+        }
+    }
+}
+
+impl From<QueryRejection> for AppError {
+    fn from(rejection: QueryRejection) -> Self {
+        AppError::Validation(vec![FieldError {
+            field: "query".to_string(),
+            message: rejection.body_text(),
+        }])
+    }
+}
+
+impl From<PathRejection> for AppError {
+    fn from(rejection: PathRejection) -> Self {
+        match rejection {
+            PathRejection::FailedToDeserializePathParams(_) => {
+                AppError::InvalidRequest("Invalid path parameter")
+            }
+            _ => AppError::Internal("Failed to extract path parameters"),
+        }
+    }
+}
@@ -1,135 +1,251 @@
+use crate::error::{AppError, FieldError};
+use crate::extract::{Json, Path, Query};
+use crate::middlewares::request_id::RequestId;
 use crate::models::{
-    AccountResponse, CreateAccountRequest, CreditDebitRequest, CreditDebitResponse,
-    GetAccountsQuery, TransferRequest, TransferResponse,
+    AccountDetailResponse, AccountLookupResponse, AccountResponse, BalanceQuery, BalanceResponse,
+    CreateAccountRequest, CreditDebitRequest, CreditDebitResponse, GetAccountsQuery, ListResponse,
+    PageParams, TransferRequest, TransferResponse,
 };
 use crate::services::accounts::{
-    check_idempotency_cache, create_cd_record, create_transaction_record, create_webhook_event,
-    execute_balance_transfer, fail_idempotency_key, fetch_account, fetch_and_validate_accounts,
-    reserve_idempotency_key, store_idempotency_key, update_balance, validate_cd_input,
-    validate_transfer_input,
+    balance_as_of, check_idempotency_cache, create_webhook_event, execute_credit_debit,
+    execute_transfer, fail_idempotency_key, fetch_and_validate_accounts, get_account,
+    reserve_idempotency_key, store_idempotency_key, validate_cd_input, validate_transfer_input,
 };
+use crate::services::holds::HELD_AMOUNT;
+use crate::services::ledger::{self, JournalEntry, LedgerAccount, SystemAccount};
+use crate::services::pagination::{Cursor, Page};
+use crate::services::retry::with_retry;
+use crate::services::settlement::create_pending_transfer_record;
+use crate::services::timestamps::parse_rfc3339;
 use crate::state::AppState;
-use axum::{
-    extract::{Extension, Query, State},
-    Json,
-};
-use serde_json::{json, Value};
+use axum::extract::{Extension, State};
+use chrono::Utc;
 use sqlx::{types::Uuid, Row};
+use tracing::instrument;
+
+/// Balance every new account is opened with, in minor units.
+const OPENING_BALANCE: i64 = 10000;
 
+#[instrument(skip_all, fields(%business_id))]
 pub async fn create_account(
     State(state): State<AppState>,
     Extension(business_id): Extension<Uuid>,
     Json(payload): Json<CreateAccountRequest>,
-) -> Result<Json<AccountResponse>, Json<Value>> {
+) -> Result<Json<AccountResponse>, AppError> {
+    // Stored uppercase, as amount limits and currency checks compare it exactly
+    let currency = payload.currency.trim().to_ascii_uppercase();
+    if currency.len() != 3 || !currency.bytes().all(|b| b.is_ascii_alphabetic()) {
+        return Err(AppError::Validation(vec![FieldError {
+            field: "currency".to_string(),
+            message: "must be a three-letter ISO 4217 code".to_string(),
+        }]));
+    }
+
     // Determine business details first to ensure we can return them
     let row = sqlx::query("SELECT name, email FROM businesses WHERE id = $1")
         .bind(business_id)
         .fetch_optional(&state.pool)
         .await
-        .map_err(|_| Json(json!({ "error": "Failed to fetch business details" })))?;
+        .map_err(|_| AppError::Internal("Failed to fetch business details"))?;
 
     let (business_name, business_email) = match row {
         Some(r) => (r.get("name"), r.get("email")),
-        None => return Err(Json(json!({ "error": "Business not found" }))),
+        None => return Err(AppError::NotFound("Business not found")),
     };
 
-    let result = sqlx::query(
-        "INSERT INTO accounts (business_id, currency, balance) VALUES ($1, $2, 10000) RETURNING id, balance"
+    let mut tx = state
+        .pool
+        .begin()
+        .await
+        .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+    let id: Uuid =
+        sqlx::query("INSERT INTO accounts (business_id, currency) VALUES ($1, $2) RETURNING id")
+            .bind(business_id)
+            .bind(&currency)
+            .fetch_one(&mut *tx)
+            .await
+            .map(|row| row.get("id"))
+            .map_err(|_| AppError::Internal("Failed to create account"))?;
+
+    ledger::post(
+        &mut tx,
+        &JournalEntry::movement(
+            "opening_balance",
+            &currency,
+            None,
+            LedgerAccount::System(SystemAccount::OpeningBalances),
+            LedgerAccount::Customer(id),
+            OPENING_BALANCE,
+        ),
     )
-    .bind(business_id)
-    .bind(&payload.currency)
-    .fetch_one(&state.pool)
-    .await;
+    .await?;
 
-    match result {
-        Ok(row) => {
-            let id: Uuid = row.get("id");
-            let balance: i64 = row.get("balance");
-            Ok(Json(AccountResponse {
-                id: id.to_string(),
-                business_id: business_id.to_string(),
-                balance,
-                currency: payload.currency,
-                business_name,
-                business_email,
-            }))
-        }
-        Err(_) => Err(Json(json!({ "error": "Failed to create account" }))),
-    }
+    tx.commit()
+        .await
+        .map_err(|_| AppError::Internal("Failed to commit transaction"))?;
+
+    Ok(Json(AccountResponse {
+        id: id.to_string(),
+        business_id: business_id.to_string(),
+        balance: OPENING_BALANCE,
+        available_balance: OPENING_BALANCE,
+        currency,
+        business_name,
+        business_email,
+    }))
 }
 
+/// Lists the caller's own accounts.
+#[instrument(skip_all, fields(%business_id))]
 pub async fn get_accounts(
     State(state): State<AppState>,
+    Extension(business_id): Extension<Uuid>,
     Query(params): Query<GetAccountsQuery>,
-) -> Result<Json<Vec<AccountResponse>>, Json<Value>> {
+    Query(page_params): Query<PageParams>,
+) -> Result<Json<ListResponse<AccountResponse>>, AppError> {
+    let page = Page::from_params(&page_params)?;
+
     let mut query_str =
-        String::from("SELECT a.id, a.business_id, a.balance, a.currency, b.name as business_name, b.email as business_email 
+        format!("SELECT a.id, a.business_id, a.balance, a.balance - {HELD_AMOUNT} AS available_balance, a.currency, a.created_at, b.name as business_name, b.email as business_email 
                       FROM accounts a 
                       JOIN businesses b ON a.business_id = b.id 
-                      WHERE 1=1");
-    let mut conditions = Vec::new();
+                      WHERE a.business_id = $1");
+    let mut next_param = 2;
 
     if params.currency.is_some() {
-        conditions.push("a.currency");
-    }
-    if params.business_id.is_some() {
-        conditions.push("a.business_id");
+        query_str.push_str(&format!(" AND a.currency = ${}", next_param));
+        next_param += 1;
     }
 
-    for (idx, condition) in conditions.iter().enumerate() {
-        query_str.push_str(&format!(" AND {} = ${}", condition, idx + 1));
+    if page.after.is_some() {
+        query_str.push_str(&format!(
+            " AND (a.created_at, a.id) {} (${}, ${})",
+            page.after_operator(),
+            next_param,
+            next_param + 1
+        ));
+        next_param += 2;
     }
 
-    query_str.push_str(" ORDER BY a.created_at");
+    query_str.push_str(&format!(
+        " ORDER BY a.created_at {dir}, a.id {dir} LIMIT ${}",
+        next_param,
+        dir = page.direction()
+    ));
 
-    let mut query = sqlx::query(&query_str);
+    let mut query = sqlx::query(&query_str).bind(business_id);
 
     if let Some(ref currency) = params.currency {
         query = query.bind(currency);
     }
-    if let Some(ref business_id_str) = params.business_id {
-        match Uuid::parse_str(business_id_str) {
-            Ok(business_id) => {
-                query = query.bind(business_id);
-            }
-            Err(_) => {
-                return Err(Json(json!({ "error": "Invalid business_id format" })));
-            }
-        }
+    if let Some(cursor) = page.after {
+        query = query.bind(cursor.created_at).bind(cursor.id);
     }
+    query = query.bind(page.fetch_limit());
 
     let result = query.fetch_all(&state.pool).await;
 
     match result {
         Ok(rows) => {
-            let accounts: Vec<AccountResponse> = rows
+            let accounts = rows
                 .into_iter()
-                .map(|row| AccountResponse {
-                    id: row.get::<Uuid, _>("id").to_string(),
-                    business_id: row.get::<Uuid, _>("business_id").to_string(),
-                    balance: row.get("balance"),
-                    currency: row.get("currency"),
-                    business_name: row.get("business_name"),
-                    business_email: row.get("business_email"),
+                .map(|row| {
+                    let id: Uuid = row.get("id");
+                    let cursor = Cursor {
+                        created_at: row.get("created_at"),
+                        id,
+                    };
+                    let account = AccountResponse {
+                        id: id.to_string(),
+                        business_id: row.get::<Uuid, _>("business_id").to_string(),
+                        balance: row.get("balance"),
+                        available_balance: row.get("available_balance"),
+                        currency: row.get("currency"),
+                        business_name: row.get("business_name"),
+                        business_email: row.get("business_email"),
+                    };
+                    (cursor, account)
                 })
                 .collect();
-            Ok(Json(accounts))
+            Ok(Json(page.finish(accounts)))
         }
-        Err(_) => Err(Json(json!({ "error": "Failed to fetch accounts" }))),
+        Err(_) => Err(AppError::Internal("Failed to fetch accounts")),
     }
 }
 
+#[instrument(skip_all, fields(%business_id))]
+pub async fn get_account_handler(
+    State(state): State<AppState>,
+    Extension(business_id): Extension<Uuid>,
+    Path(account_id): Path<String>,
+) -> Result<Json<AccountDetailResponse>, AppError> {
+    let account_id = Uuid::parse_str(&account_id)
+        .map_err(|_| AppError::InvalidRequest("Invalid account_id format"))?;
+    let response = get_account(&state, business_id, account_id).await?;
+    Ok(Json(response))
+}
+
+#[instrument(skip_all, fields(%business_id))]
+pub async fn get_balance_handler(
+    State(state): State<AppState>,
+    Extension(business_id): Extension<Uuid>,
+    Path(account_id): Path<String>,
+    Query(query): Query<BalanceQuery>,
+) -> Result<Json<BalanceResponse>, AppError> {
+    let account_id = Uuid::parse_str(&account_id)
+        .map_err(|_| AppError::InvalidRequest("Invalid account_id format"))?;
+    let as_of = match query.as_of {
+        Some(value) => parse_rfc3339(&value).ok_or(AppError::InvalidRequest(
+            "as_of must be an RFC 3339 timestamp",
+        ))?,
+        None => Utc::now().naive_utc(),
+    };
+    let response = balance_as_of(&state, business_id, account_id, as_of).await?;
+    Ok(Json(response))
+}
+
+/// Confirms that an account exists and reports its currency, so a business
+/// can check a payee before transferring. Reveals nothing about the owner.
+#[instrument(skip_all)]
+pub async fn lookup_account(
+    State(state): State<AppState>,
+    Path(account_id): Path<String>,
+) -> Result<Json<AccountLookupResponse>, AppError> {
+    let account_id = Uuid::parse_str(&account_id)
+        .map_err(|_| AppError::InvalidRequest("Invalid account_id format"))?;
+
+    let row = sqlx::query("SELECT currency FROM accounts WHERE id = $1")
+        .bind(account_id)
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(|_| AppError::Internal("Failed to fetch account"))?
+        .ok_or(AppError::NotFound("Account not found"))?;
+
+    Ok(Json(AccountLookupResponse {
+        id: account_id.to_string(),
+        currency: row.get("currency"),
+    }))
+}
+
+#[instrument(skip_all, fields(%business_id))]
 pub async fn transfer(
     State(state): State<AppState>,
     Extension(business_id): Extension<Uuid>,
+    Extension(request_id): Extension<RequestId>,
     Json(payload): Json<TransferRequest>,
-) -> Result<Json<TransferResponse>, Json<Value>> {
+) -> Result<Json<TransferResponse>, AppError> {
     let (from_account_id, to_account_id) = validate_transfer_input(&payload)?;
 
     if let Some(mut cached_response) =
         check_idempotency_cache::<TransferResponse>(&state, business_id, &payload.idempotency_key)
             .await?
     {
+        state
+            .metrics
+            .idempotency_cache_hits
+            .with_label_values(&["transfer"])
+            .inc();
         cached_response.cached = Some(true);
         return Ok(Json(cached_response));
     }
@@ -137,54 +253,85 @@ pub async fn transfer(
     // Reserve idempotency key
     reserve_idempotency_key(&state, business_id, &payload.idempotency_key).await?;
 
-    let process_transfer = async {
+    // Opposing transfers between the same accounts can still conflict at
+    // commit, so a deadlock or serialization failure retries the transfer
+    let process_transfer = with_retry(|| async {
         let mut tx = state
             .pool
             .begin()
             .await
-            .map_err(|_| Json(json!({ "error": "Failed to start transaction" })))?;
-
-        let (currency, _) = fetch_and_validate_accounts(
-            &mut tx,
-            from_account_id,
-            to_account_id,
-            business_id,
-            payload.amount,
-        )
-        .await?;
-
-        execute_balance_transfer(&mut tx, from_account_id, to_account_id, payload.amount).await?;
-
-        let transaction_id = create_transaction_record(
-            &mut tx,
-            business_id,
-            from_account_id,
-            to_account_id,
-            payload.amount,
-            &payload.idempotency_key,
-        )
-        .await?;
-
-        let response = TransferResponse {
-            transaction_id: transaction_id.to_string(),
-            from_account_id: payload.from_account_id.clone(),
-            to_account_id: payload.to_account_id.clone(),
-            amount: payload.amount,
-            currency,
-            status: "success".to_string(),
-            cached: None,
+            .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+        let response = if payload.pending {
+            let (currency, _) = fetch_and_validate_accounts(
+                &mut tx,
+                &state.config.amount_limits,
+                from_account_id,
+                to_account_id,
+                business_id,
+                payload.amount,
+            )
+            .await?;
+
+            // Funds stay on the source account, held, until settlement
+            let transaction_id = create_pending_transfer_record(
+                &mut tx,
+                business_id,
+                from_account_id,
+                to_account_id,
+                payload.amount,
+                &payload.idempotency_key,
+                request_id.as_str(),
+            )
+            .await?;
+
+            let response = TransferResponse {
+                transaction_id: transaction_id.to_string(),
+                from_account_id: from_account_id.to_string(),
+                to_account_id: to_account_id.to_string(),
+                amount: payload.amount,
+                currency,
+                status: "pending".to_string(),
+                cached: None,
+            };
+            create_webhook_event(
+                &mut tx,
+                business_id,
+                "transfer.created",
+                &response,
+                request_id.as_str(),
+            )
+            .await?;
+            response
+        } else {
+            let (_, response) = execute_transfer(
+                &mut tx,
+                &state.config.amount_limits,
+                business_id,
+                from_account_id,
+                to_account_id,
+                payload.amount,
+                &payload.idempotency_key,
+                request_id.as_str(),
+            )
+            .await?;
+            response
         };
 
-        create_webhook_event(&mut tx, business_id, "transfer.created", &response).await?;
-
         store_idempotency_key(&mut tx, business_id, &payload.idempotency_key, &response).await?;
 
         tx.commit()
             .await
-            .map_err(|_| Json(json!({ "error": "Failed to commit transaction" })))?;
+            .map_err(AppError::database("Failed to commit transaction"))?;
+
+        if !payload.pending {
+            state
+                .metrics
+                .record_money_movement("transfer", &response.currency, response.amount);
+        }
 
         Ok(Json(response))
-    };
+    });
 
     match process_transfer.await {
         Ok(response) => Ok(response),
@@ -195,11 +342,13 @@ pub async fn transfer(
     }
 }
 
+#[instrument(skip_all, fields(%business_id))]
 pub async fn credit_debit(
     State(state): State<AppState>,
     Extension(business_id): Extension<Uuid>,
+    Extension(request_id): Extension<RequestId>,
     Json(payload): Json<CreditDebitRequest>,
-) -> Result<Json<CreditDebitResponse>, Json<Value>> {
+) -> Result<Json<CreditDebitResponse>, AppError> {
     let account_id = validate_cd_input(&payload)?;
 
     // Check idempotency cache
@@ -210,6 +359,11 @@ pub async fn credit_debit(
     )
     .await?
     {
+        state
+            .metrics
+            .idempotency_cache_hits
+            .with_label_values(&["credit_debit"])
+            .inc();
         cached_response.cached = Some(true);
         return Ok(Json(cached_response));
     }
@@ -217,65 +371,40 @@ pub async fn credit_debit(
     // Reserve idempotency key
     reserve_idempotency_key(&state, business_id, &payload.idempotency_key).await?;
 
-    let is_credit = payload.transaction_type == "credit";
-
-    let process_cd = async {
+    let process_cd = with_retry(|| async {
         let mut tx = state
             .pool
             .begin()
             .await
-            .map_err(|_| Json(json!({ "error": "Failed to start transaction" })))?;
-
-        // Fetch and validate account
-        let (currency, current_balance) = fetch_account(&mut tx, account_id, business_id).await?;
-
-        // For debit, check sufficient balance
-        if !is_credit && current_balance < payload.amount {
-            return Err(Json(json!({
-                "error": "Insufficient balance",
-                "available": current_balance,
-                "required": payload.amount
-            })));
-        }
-
-        // Update balance
-        let new_balance = update_balance(&mut tx, account_id, payload.amount, is_credit).await?;
+            .map_err(|_| AppError::Internal("Failed to start transaction"))?;
 
-        // Create transaction record
-        let transaction_id = create_cd_record(
+        let (_, response) = execute_credit_debit(
             &mut tx,
+            &state.config.amount_limits,
             business_id,
             account_id,
             payload.amount,
             &payload.transaction_type,
             &payload.idempotency_key,
+            request_id.as_str(),
         )
         .await?;
 
-        let response = CreditDebitResponse {
-            transaction_id: transaction_id.to_string(),
-            account_id: payload.account_id.clone(),
-            amount: payload.amount,
-            currency,
-            transaction_type: payload.transaction_type.clone(),
-            status: "success".to_string(),
-            new_balance,
-            cached: None,
-        };
-
-        // Create webhook event
-        let event_type = format!("{}.created", payload.transaction_type);
-        create_webhook_event(&mut tx, business_id, &event_type, &response).await?;
-
         // Store idempotency key with response
         store_idempotency_key(&mut tx, business_id, &payload.idempotency_key, &response).await?;
 
         tx.commit()
             .await
-            .map_err(|_| Json(json!({ "error": "Failed to commit transaction" })))?;
+            .map_err(AppError::database("Failed to commit transaction"))?;
+
+        state.metrics.record_money_movement(
+            &response.transaction_type,
+            &response.currency,
+            response.amount,
+        );
 
         Ok(Json(response))
-    };
+    });
 
     match process_cd.await {
         Ok(response) => Ok(response),
@@ -0,0 +1,16 @@
+use crate::error::AppError;
+use crate::models::LedgerVerificationReport;
+use crate::services::ledger;
+use crate::state::AppState;
+use axum::{extract::State, Json};
+use tracing::instrument;
+
+/// Recomputes every balance from the ledger and reports any discrepancies.
+#[instrument(skip_all)]
+pub async fn verify_ledger_handler(
+    State(state): State<AppState>,
+) -> Result<Json<LedgerVerificationReport>, AppError> {
+    let report = ledger::verify(&state.pool).await?;
+    state.metrics.record_ledger_verification(&report);
+    Ok(Json(report))
+}
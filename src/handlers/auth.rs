@@ -1,12 +1,19 @@
+use crate::error::AppError;
+use crate::extract::Json;
+use crate::models::Permission;
+use crate::services::auth::{
+    create_session, generate_secret, hash_password, hash_token, request_password_reset,
+    reset_password, revoke_refresh_token, rotate_refresh_token, verify_credentials, SessionTokens,
+    API_KEY_PREFIX,
+};
+use crate::services::two_factor::SecondFactor;
+use crate::services::validation::{normalize_email, Validator};
 use crate::state::AppState;
-use axum::{extract::State, Json};
-use bcrypt::{hash, verify, DEFAULT_COST};
-use hex;
-use rand::Rng;
+use axum::extract::State;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use sha2::{Digest, Sha256};
-use sqlx::Row;
+use sqlx::{types::Uuid, Row};
+use tracing::instrument;
 
 #[derive(Deserialize)]
 pub struct SignupRequest {
@@ -19,6 +26,8 @@ pub struct SignupRequest {
 pub struct GenerateApiKeyRequest {
     email: String,
     password: String,
+    #[serde(flatten)]
+    second_factor: SecondFactor,
 }
 
 #[derive(Serialize)]
@@ -26,73 +35,181 @@ pub struct GenerateApiKeyResponse {
     api_key: String,
 }
 
+#[derive(Deserialize)]
+pub struct LoginRequest {
+    email: String,
+    password: String,
+    #[serde(flatten)]
+    second_factor: SecondFactor,
+}
+
+#[derive(Deserialize)]
+pub struct RefreshTokenRequest {
+    refresh_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct ForgotPasswordRequest {
+    email: String,
+}
+
+#[derive(Deserialize)]
+pub struct ResetPasswordRequest {
+    reset_token: String,
+    new_password: String,
+}
+
+#[instrument(skip_all)]
 pub async fn signup(
     State(state): State<AppState>,
     Json(payload): Json<SignupRequest>,
-) -> Result<Json<Value>, Json<Value>> {
-    let password_hash = hash(&payload.password, DEFAULT_COST)
-        .map_err(|_| Json(json!({ "error": "Failed to hash password" })))?;
+) -> Result<Json<Value>, AppError> {
+    let email = normalize_email(&payload.email);
+    let name = payload.name.trim();
+
+    let mut validator = Validator::default();
+    validator.email("email", &email);
+    validator.password("password", &payload.password);
+    validator.name("name", name);
+    validator.finish()?;
+
+    let password_hash = hash_password(&payload.password).await?;
+
+    let mut tx = state
+        .pool
+        .begin()
+        .await
+        .map_err(|_| AppError::Internal("Failed to start transaction"))?;
 
     let result = sqlx::query(
         "INSERT INTO businesses (email, password_hash, name) VALUES ($1, $2, $3) RETURNING id",
     )
-    .bind(&payload.email)
+    .bind(&email)
     .bind(&password_hash)
-    .bind(&payload.name)
-    .fetch_one(&state.pool)
+    .bind(name)
+    .fetch_one(&mut *tx)
     .await;
 
-    match result {
-        Ok(row) => {
-            let id: sqlx::types::Uuid = row.get("id");
-            Ok(Json(
-                json!({ "id": id.to_string(), "email": payload.email, "name": payload.name }),
-            ))
+    let id: Uuid = match result {
+        Ok(row) => row.get("id"),
+        Err(sqlx::Error::Database(e)) if e.constraint().is_some() => {
+            return Err(AppError::AlreadyExists("Email already exists"));
         }
+        Err(_) => return Err(AppError::Internal("Failed to create business")),
+    };
+
+    // The signing-up user becomes the business owner.
+    let result = sqlx::query(
+        "INSERT INTO users (business_id, email, password_hash, name, role) 
+         VALUES ($1, $2, $3, $4, 'owner'::user_role)",
+    )
+    .bind(id)
+    .bind(&email)
+    .bind(&password_hash)
+    .bind(name)
+    .execute(&mut *tx)
+    .await;
+
+    match result {
+        Ok(_) => {}
         Err(sqlx::Error::Database(e)) if e.constraint().is_some() => {
-            Err(Json(json!({ "error": "Email already exists" })))
+            return Err(AppError::AlreadyExists("Email already exists"));
         }
-        Err(_) => Err(Json(json!({ "error": "Failed to create business" }))),
+        Err(_) => return Err(AppError::Internal("Failed to create business")),
     }
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::Internal("Failed to commit transaction"))?;
+
+    Ok(Json(
+        json!({ "id": id.to_string(), "email": email, "name": name }),
+    ))
 }
 
+#[instrument(skip_all)]
 pub async fn generate_api_key(
     State(state): State<AppState>,
     Json(payload): Json<GenerateApiKeyRequest>,
-) -> Result<Json<GenerateApiKeyResponse>, Json<Value>> {
-    let row = sqlx::query("SELECT id, password_hash FROM businesses WHERE email = $1")
-        .bind(&payload.email)
-        .fetch_optional(&state.pool)
-        .await
-        .map_err(|_| Json(json!({ "error": "Database error" })))?;
-
-    let (business_id, password_hash) = match row {
-        Some(row) => (
-            row.get::<sqlx::types::Uuid, _>("id"),
-            row.get::<String, _>("password_hash"),
-        ),
-        None => return Err(Json(json!({ "error": "Invalid credentials" }))),
-    };
+) -> Result<Json<GenerateApiKeyResponse>, AppError> {
+    let user = verify_credentials(
+        &state,
+        &payload.email,
+        &payload.password,
+        &payload.second_factor,
+    )
+    .await?;
 
-    if !verify(&payload.password, &password_hash).unwrap_or(false) {
-        return Err(Json(json!({ "error": "Invalid credentials" })));
+    if !Permission::ManageApiKeys.allows(user.role) {
+        return Err(AppError::Forbidden("Insufficient permissions"));
     }
 
-    let api_key = format!(
-        "sk_live_{}",
-        hex::encode(rand::thread_rng().gen::<[u8; 32]>())
-    );
-    let mut hasher = Sha256::new();
-    hasher.update(api_key.as_bytes());
-    let key_hash = hex::encode(hasher.finalize());
-
-    sqlx::query("INSERT INTO api_keys (business_id, key_hash, is_active) VALUES ($1, $2, true)")
-        .bind(business_id)
-        .bind(&key_hash)
-        .execute(&state.pool)
-        .await
-        .map_err(|_| Json(json!({ "error": "Failed to create API key" })))?;
+    let api_key = generate_secret(API_KEY_PREFIX);
+
+    // Keys inherit the role of the user who minted them.
+    sqlx::query(
+        "INSERT INTO api_keys (business_id, key_hash, is_active, role, created_by) VALUES ($1, $2, true, $3, $4)",
+    )
+    .bind(user.business_id)
+    .bind(hash_token(&api_key))
+    .bind(user.role)
+    .bind(user.user_id)
+    .execute(&state.pool)
+    .await
+    .map_err(|_| AppError::Internal("Failed to create API key"))?;
 
     Ok(Json(GenerateApiKeyResponse { api_key }))
 }
 
+#[instrument(skip_all)]
+pub async fn login(
+    State(state): State<AppState>,
+    Json(payload): Json<LoginRequest>,
+) -> Result<Json<SessionTokens>, AppError> {
+    let user = verify_credentials(
+        &state,
+        &payload.email,
+        &payload.password,
+        &payload.second_factor,
+    )
+    .await?;
+    let tokens = create_session(&state, &user).await?;
+    Ok(Json(tokens))
+}
+
+#[instrument(skip_all)]
+pub async fn refresh(
+    State(state): State<AppState>,
+    Json(payload): Json<RefreshTokenRequest>,
+) -> Result<Json<SessionTokens>, AppError> {
+    let tokens = rotate_refresh_token(&state, &payload.refresh_token).await?;
+    Ok(Json(tokens))
+}
+
+#[instrument(skip_all)]
+pub async fn logout(
+    State(state): State<AppState>,
+    Json(payload): Json<RefreshTokenRequest>,
+) -> Result<Json<Value>, AppError> {
+    revoke_refresh_token(&state, &payload.refresh_token).await?;
+    Ok(Json(json!({ "status": "logged_out" })))
+}
+
+#[instrument(skip_all)]
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    Json(payload): Json<ForgotPasswordRequest>,
+) -> Result<Json<Value>, AppError> {
+    request_password_reset(&state, &payload.email).await?;
+    // Same response whether or not the email exists
+    Ok(Json(json!({ "status": "reset_requested" })))
+}
+
+#[instrument(skip_all)]
+pub async fn reset_password_handler(
+    State(state): State<AppState>,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> Result<Json<Value>, AppError> {
+    reset_password(&state, &payload.reset_token, &payload.new_password).await?;
+    Ok(Json(json!({ "status": "password_reset" })))
+}
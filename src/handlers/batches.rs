@@ -0,0 +1,133 @@
+use crate::error::AppError;
+use crate::extract::{Json, Path};
+use crate::middlewares::request_id::RequestId;
+use crate::models::{BatchResponse, BatchTransferRequest};
+use crate::services::accounts::{
+    check_idempotency_cache, fail_idempotency_key, reserve_idempotency_key, store_idempotency_key,
+};
+use crate::services::batches::{
+    create_batch, fetch_batch, fetch_batch_summary, process_batch, validate_batch_items,
+    SYNC_BATCH_SIZE,
+};
+use crate::services::limits::check_all_amount_limits;
+use crate::state::AppState;
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+};
+use sqlx::types::Uuid;
+use tracing::instrument;
+
+/// `202 Accepted` until the batch has finished running.
+fn batch_status_code(batch: &BatchResponse) -> StatusCode {
+    match batch.status.as_str() {
+        "completed" | "failed" => StatusCode::OK,
+        _ => StatusCode::ACCEPTED,
+    }
+}
+
+/// Creates many transfers under one idempotency key. Small batches run
+/// before responding; larger ones are queued and polled through
+/// `GET /transfers/batch/{id}`.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn create_batch_handler(
+    State(state): State<AppState>,
+    Extension(business_id): Extension<Uuid>,
+    Extension(request_id): Extension<RequestId>,
+    Json(payload): Json<BatchTransferRequest>,
+) -> Result<(StatusCode, Json<BatchResponse>), AppError> {
+    let items = validate_batch_items(&payload.transfers)?;
+
+    // Replays report the batch as it is now, not as it was first accepted
+    if let Some(cached_response) =
+        check_idempotency_cache::<BatchResponse>(&state, business_id, &payload.idempotency_key)
+            .await?
+    {
+        state
+            .metrics
+            .idempotency_cache_hits
+            .with_label_values(&["batch"])
+            .inc();
+        let batch_id = Uuid::parse_str(&cached_response.id)
+            .map_err(|_| AppError::Internal("Failed to read cached batch"))?;
+        let mut batch = fetch_batch(&state.pool, business_id, batch_id).await?;
+        batch.cached = Some(true);
+        return Ok((batch_status_code(&batch), Json(batch)));
+    }
+
+    let entries: Vec<_> = items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            (
+                format!("transfers[{index}].amount"),
+                item.from_account_id,
+                item.amount,
+            )
+        })
+        .collect();
+    check_all_amount_limits(&state, &entries).await?;
+
+    reserve_idempotency_key(&state, business_id, &payload.idempotency_key).await?;
+
+    let queued = items.len() > SYNC_BATCH_SIZE;
+
+    let store_batch = async {
+        let mut tx = state
+            .pool
+            .begin()
+            .await
+            .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+        let batch_id = create_batch(
+            &mut tx,
+            business_id,
+            &payload.idempotency_key,
+            payload.mode,
+            &items,
+            queued,
+            request_id.as_str(),
+        )
+        .await?;
+        let summary = fetch_batch_summary(&mut *tx, business_id, batch_id).await?;
+
+        store_idempotency_key(&mut tx, business_id, &payload.idempotency_key, &summary).await?;
+
+        tx.commit()
+            .await
+            .map_err(|_| AppError::Internal("Failed to commit transaction"))?;
+
+        Ok(batch_id)
+    };
+
+    let batch_id = match store_batch.await {
+        Ok(batch_id) => batch_id,
+        Err(err) => {
+            let _ = fail_idempotency_key(&state, business_id, &payload.idempotency_key).await;
+            return Err(err);
+        }
+    };
+
+    // The batch is stored, so a failure here is retried by the worker rather
+    // than reported to the client
+    if !queued {
+        if let Err(e) = process_batch(&state, batch_id).await {
+            tracing::error!(error = ?e, %batch_id, "Failed to run batch");
+        }
+    }
+
+    let batch = fetch_batch(&state.pool, business_id, batch_id).await?;
+    Ok((batch_status_code(&batch), Json(batch)))
+}
+
+#[instrument(skip_all, fields(%business_id))]
+pub async fn get_batch_handler(
+    State(state): State<AppState>,
+    Extension(business_id): Extension<Uuid>,
+    Path(batch_id): Path<String>,
+) -> Result<(StatusCode, Json<BatchResponse>), AppError> {
+    let batch_id = Uuid::parse_str(&batch_id)
+        .map_err(|_| AppError::InvalidRequest("Invalid batch_id format"))?;
+    let batch = fetch_batch(&state.pool, business_id, batch_id).await?;
+    Ok((StatusCode::OK, Json(batch)))
+}
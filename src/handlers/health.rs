@@ -1,22 +1,107 @@
-use axum::{extract::State, Json};
-use serde_json::{json, Value};
 use crate::state::AppState;
+use axum::{extract::State, http::StatusCode, Json};
+use serde_json::{json, Value};
+use sqlx::Row;
+use std::time::Duration;
+
+/// Schema version this build expects; must match the `schema_version` stamp at
+/// the end of `migrations/initial_setup.sql`.
+pub const SCHEMA_VERSION: i32 = 11;
+
+/// The webhook worker beats at least every poll interval; allow for a batch of
+/// slow deliveries before calling it stuck.
+const WORKER_HEARTBEAT_MAX_AGE: Duration = Duration::from_secs(60);
 
-pub async fn health_check(State(state): State<AppState>) -> Json<Value> {
+pub async fn health_check(State(state): State<AppState>) -> (StatusCode, Json<Value>) {
     // Check PostgreSQL connection
-    match sqlx::query("SELECT 1")
-        .execute(&state.pool)
+    match sqlx::query("SELECT 1").execute(&state.pool).await {
+        Ok(_) => (
+            StatusCode::OK,
+            Json(json!({
+                "status": "healthy",
+                "database": "connected"
+            })),
+        ),
+        Err(e) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({
+                "status": "unhealthy",
+                "database": "disconnected",
+                "error": e.to_string()
+            })),
+        ),
+    }
+}
+
+/// Liveness: the process is up and serving requests. Never touches
+/// dependencies, so a database outage does not get the pod restarted.
+pub async fn liveness() -> Json<Value> {
+    Json(json!({ "status": "alive" }))
+}
+
+/// Readiness: every dependency needed to serve traffic is usable.
+pub async fn readiness(State(state): State<AppState>) -> (StatusCode, Json<Value>) {
+    let database = match sqlx::query("SELECT 1").execute(&state.pool).await {
+        Ok(_) => json!({ "status": "ok" }),
+        Err(e) => json!({ "status": "failing", "error": e.to_string() }),
+    };
+
+    let migrations = match sqlx::query("SELECT version FROM schema_version")
+        .fetch_one(&state.pool)
         .await
     {
-        Ok(_) => Json(json!({
-            "status": "healthy",
-            "database": "connected"
-        })),
-        Err(e) => Json(json!({
-            "status": "unhealthy",
-            "database": "disconnected",
+        Ok(row) => {
+            let version: i32 = row.get("version");
+            let status = if version == SCHEMA_VERSION {
+                "ok"
+            } else {
+                "failing"
+            };
+            json!({ "status": status, "version": version, "expected": SCHEMA_VERSION })
+        }
+        Err(e) => json!({
+            "status": "failing",
+            "expected": SCHEMA_VERSION,
             "error": e.to_string()
+        }),
+    };
+
+    let webhook_worker = match state.webhook_heartbeat.age() {
+        Some(age) if age <= WORKER_HEARTBEAT_MAX_AGE => {
+            json!({ "status": "ok", "last_heartbeat_seconds_ago": age.as_secs() })
+        }
+        Some(age) => json!({ "status": "failing", "last_heartbeat_seconds_ago": age.as_secs() }),
+        None => json!({ "status": "failing", "error": "worker has not started" }),
+    };
+
+    let ready = [&database, &migrations, &webhook_worker]
+        .iter()
+        .all(|check| check["status"] == "ok");
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        status,
+        Json(json!({
+            "status": if ready { "ready" } else { "not_ready" },
+            "checks": {
+                "database": database,
+                "migrations": migrations,
+                "webhook_worker": webhook_worker,
+            }
         })),
-    }
+    )
 }
 
+/// Build information for support and deploy verification.
+pub async fn build_info() -> Json<Value> {
+    Json(json!({
+        "name": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "git_sha": option_env!("GIT_SHA").unwrap_or("unknown"),
+        "schema_version": SCHEMA_VERSION,
+    }))
+}
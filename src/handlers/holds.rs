@@ -0,0 +1,259 @@
+use crate::error::{AppError, FieldError};
+use crate::extract::{Json, Path};
+use crate::middlewares::request_id::RequestId;
+use crate::models::{CaptureHoldRequest, CreateHoldRequest, HoldResponse};
+use crate::services::accounts::{
+    check_idempotency_cache, create_webhook_event, execute_credit_debit, execute_transfer,
+    fail_idempotency_key, reserve_idempotency_key, store_idempotency_key,
+};
+use crate::services::holds::{
+    close_hold, create_hold, fetch_hold, hold_ttl, link_hold_transaction, lock_active_hold,
+};
+use crate::services::limits::check_amount_limits;
+use crate::services::retry::with_retry;
+use crate::state::AppState;
+use axum::extract::{Extension, State};
+use sqlx::types::Uuid;
+use tracing::instrument;
+
+fn parse_hold_id(hold_id: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(hold_id).map_err(|_| AppError::InvalidRequest("Invalid hold_id format"))
+}
+
+#[instrument(skip_all, fields(%business_id))]
+pub async fn create_hold_handler(
+    State(state): State<AppState>,
+    Extension(business_id): Extension<Uuid>,
+    Extension(request_id): Extension<RequestId>,
+    Json(payload): Json<CreateHoldRequest>,
+) -> Result<Json<HoldResponse>, AppError> {
+    if payload.amount <= 0 {
+        return Err(AppError::InvalidRequest("Amount must be positive"));
+    }
+    let account_id = Uuid::parse_str(&payload.account_id)
+        .map_err(|_| AppError::InvalidRequest("Invalid account_id format"))?;
+    let ttl_secs = hold_ttl(payload.expires_in_seconds)?;
+
+    if let Some(mut cached_response) =
+        check_idempotency_cache::<HoldResponse>(&state, business_id, &payload.idempotency_key)
+            .await?
+    {
+        state
+            .metrics
+            .idempotency_cache_hits
+            .with_label_values(&["hold"])
+            .inc();
+        cached_response.cached = Some(true);
+        return Ok(Json(cached_response));
+    }
+
+    check_amount_limits(&state, "amount", account_id, payload.amount).await?;
+
+    reserve_idempotency_key(&state, business_id, &payload.idempotency_key).await?;
+
+    let process_hold = with_retry(|| async {
+        let mut tx = state
+            .pool
+            .begin()
+            .await
+            .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+        let hold = create_hold(&mut tx, business_id, account_id, payload.amount, ttl_secs).await?;
+
+        create_webhook_event(
+            &mut tx,
+            business_id,
+            "hold.created",
+            &hold,
+            request_id.as_str(),
+        )
+        .await?;
+
+        store_idempotency_key(&mut tx, business_id, &payload.idempotency_key, &hold).await?;
+
+        tx.commit()
+            .await
+            .map_err(AppError::database("Failed to commit transaction"))?;
+
+        Ok(Json(hold))
+    });
+
+    match process_hold.await {
+        Ok(response) => Ok(response),
+        Err(err) => {
+            let _ = fail_idempotency_key(&state, business_id, &payload.idempotency_key).await;
+            Err(err)
+        }
+    }
+}
+
+#[instrument(skip_all, fields(%business_id))]
+pub async fn get_hold_handler(
+    State(state): State<AppState>,
+    Extension(business_id): Extension<Uuid>,
+    Path(hold_id): Path<String>,
+) -> Result<Json<HoldResponse>, AppError> {
+    let hold_id = parse_hold_id(&hold_id)?;
+    let hold = fetch_hold(&state.pool, business_id, hold_id).await?;
+    Ok(Json(hold))
+}
+
+/// Settles all or part of a hold as a transfer (with `to_account_id`) or a
+/// debit. A hold is captured once; any uncaptured remainder is released.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn capture_hold_handler(
+    State(state): State<AppState>,
+    Extension(business_id): Extension<Uuid>,
+    Extension(request_id): Extension<RequestId>,
+    Path(hold_id): Path<String>,
+    Json(payload): Json<CaptureHoldRequest>,
+) -> Result<Json<HoldResponse>, AppError> {
+    let hold_id = parse_hold_id(&hold_id)?;
+    if payload.amount.is_some_and(|amount| amount <= 0) {
+        return Err(AppError::InvalidRequest("Amount must be positive"));
+    }
+    let to_account_id = payload
+        .to_account_id
+        .as_deref()
+        .map(Uuid::parse_str)
+        .transpose()
+        .map_err(|_| AppError::InvalidRequest("Invalid to_account_id format"))?;
+
+    if let Some(mut cached_response) =
+        check_idempotency_cache::<HoldResponse>(&state, business_id, &payload.idempotency_key)
+            .await?
+    {
+        state
+            .metrics
+            .idempotency_cache_hits
+            .with_label_values(&["capture"])
+            .inc();
+        cached_response.cached = Some(true);
+        return Ok(Json(cached_response));
+    }
+
+    reserve_idempotency_key(&state, business_id, &payload.idempotency_key).await?;
+
+    let process_capture = with_retry(|| async {
+        let mut tx = state
+            .pool
+            .begin()
+            .await
+            .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+        let (account_id, held) = lock_active_hold(&mut tx, business_id, hold_id).await?;
+        let amount = payload.amount.unwrap_or(held);
+        if amount > held {
+            return Err(AppError::Validation(vec![FieldError {
+                field: "amount".to_string(),
+                message: format!("must be at most {}, the held amount", held),
+            }]));
+        }
+
+        // Release the reservation first so the funds checks below count it
+        // as available to this capture
+        close_hold(&mut tx, hold_id, "captured", Some(amount)).await?;
+
+        let (transaction_id, currency) = match to_account_id {
+            Some(to_account_id) => {
+                let (transaction_id, transfer) = execute_transfer(
+                    &mut tx,
+                    &state.config.amount_limits,
+                    business_id,
+                    account_id,
+                    to_account_id,
+                    amount,
+                    &payload.idempotency_key,
+                    request_id.as_str(),
+                )
+                .await?;
+                (transaction_id, transfer.currency)
+            }
+            None => {
+                let (transaction_id, debit) = execute_credit_debit(
+                    &mut tx,
+                    &state.config.amount_limits,
+                    business_id,
+                    account_id,
+                    amount,
+                    "debit",
+                    &payload.idempotency_key,
+                    request_id.as_str(),
+                )
+                .await?;
+                (transaction_id, debit.currency)
+            }
+        };
+
+        link_hold_transaction(&mut tx, hold_id, transaction_id).await?;
+        let hold = fetch_hold(&mut *tx, business_id, hold_id).await?;
+
+        create_webhook_event(
+            &mut tx,
+            business_id,
+            "hold.captured",
+            &hold,
+            request_id.as_str(),
+        )
+        .await?;
+
+        store_idempotency_key(&mut tx, business_id, &payload.idempotency_key, &hold).await?;
+
+        tx.commit()
+            .await
+            .map_err(AppError::database("Failed to commit transaction"))?;
+
+        let kind = if to_account_id.is_some() {
+            "transfer"
+        } else {
+            "debit"
+        };
+        state.metrics.record_money_movement(kind, &currency, amount);
+
+        Ok(Json(hold))
+    });
+
+    match process_capture.await {
+        Ok(response) => Ok(response),
+        Err(err) => {
+            let _ = fail_idempotency_key(&state, business_id, &payload.idempotency_key).await;
+            Err(err)
+        }
+    }
+}
+
+/// Releases a hold without moving any money.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn void_hold_handler(
+    State(state): State<AppState>,
+    Extension(business_id): Extension<Uuid>,
+    Extension(request_id): Extension<RequestId>,
+    Path(hold_id): Path<String>,
+) -> Result<Json<HoldResponse>, AppError> {
+    let hold_id = parse_hold_id(&hold_id)?;
+
+    let mut tx = state
+        .pool
+        .begin()
+        .await
+        .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+    lock_active_hold(&mut tx, business_id, hold_id).await?;
+    close_hold(&mut tx, hold_id, "voided", None).await?;
+    let hold = fetch_hold(&mut *tx, business_id, hold_id).await?;
+
+    create_webhook_event(
+        &mut tx,
+        business_id,
+        "hold.voided",
+        &hold,
+        request_id.as_str(),
+    )
+    .await?;
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::Internal("Failed to commit transaction"))?;
+
+    Ok(Json(hold))
+}
@@ -0,0 +1,37 @@
+use crate::error::AppError;
+use crate::state::AppState;
+use axum::{extract::State, http::header, response::IntoResponse};
+use sqlx::Row;
+
+/// Prometheus scrape endpoint. Gauges that mirror external state (pool usage,
+/// webhook backlog) are refreshed here rather than on every change.
+pub async fn metrics_handler(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
+    let idle = state.pool.num_idle() as i64;
+    let size = state.pool.size() as i64;
+    let pool = &state.metrics.db_pool_connections;
+    pool.with_label_values(&["idle"]).set(idle);
+    pool.with_label_values(&["in_use"]).set(size - idle);
+
+    let rows = sqlx::query(
+        "SELECT status::text AS status, COUNT(*) AS count FROM webhook_events GROUP BY status",
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(|_| AppError::Internal("Failed to count webhook events"))?;
+
+    state.metrics.webhook_events.reset();
+    for row in rows {
+        let status: String = row.get("status");
+        let count: i64 = row.get("count");
+        state
+            .metrics
+            .webhook_events
+            .with_label_values(&[status.as_str()])
+            .set(count);
+    }
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.render(),
+    ))
+}
@@ -1,4 +1,13 @@
 pub mod accounts;
+pub mod admin;
 pub mod auth;
+pub mod batches;
 pub mod health;
+pub mod holds;
+pub mod metrics;
+pub mod multi_leg;
+pub mod schedules;
+pub mod transactions;
+pub mod two_factor;
+pub mod users;
 pub mod webhooks;
@@ -0,0 +1,88 @@
+use crate::error::AppError;
+use crate::extract::Json;
+use crate::middlewares::request_id::RequestId;
+use crate::models::{MultiLegTransferRequest, MultiLegTransferResponse};
+use crate::services::accounts::{
+    check_idempotency_cache, fail_idempotency_key, reserve_idempotency_key, store_idempotency_key,
+};
+use crate::services::limits::check_amount_limits;
+use crate::services::multi_leg::{execute_multi_leg_transfer, source_total, validate_legs};
+use crate::services::retry::with_retry;
+use crate::state::AppState;
+use axum::extract::{Extension, State};
+use sqlx::types::Uuid;
+use tracing::instrument;
+
+/// Moves money from one or more of the caller's accounts to one or more
+/// destinations in a single database transaction, such as a sale split
+/// between a seller and a platform fee account.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn multi_leg_transfer_handler(
+    State(state): State<AppState>,
+    Extension(business_id): Extension<Uuid>,
+    Extension(request_id): Extension<RequestId>,
+    Json(payload): Json<MultiLegTransferRequest>,
+) -> Result<Json<MultiLegTransferResponse>, AppError> {
+    let legs = validate_legs(&payload.legs)?;
+
+    if let Some(mut cached_response) = check_idempotency_cache::<MultiLegTransferResponse>(
+        &state,
+        business_id,
+        &payload.idempotency_key,
+    )
+    .await?
+    {
+        state
+            .metrics
+            .idempotency_cache_hits
+            .with_label_values(&["multi_leg"])
+            .inc();
+        cached_response.cached = Some(true);
+        return Ok(Json(cached_response));
+    }
+
+    // Limits apply to the total leaving the sources, in their currency
+    let total = source_total(&legs).ok_or(AppError::InvalidRequest("Amount is too large"))?;
+    if let Some(source) = legs.iter().find(|leg| leg.amount < 0) {
+        check_amount_limits(&state, "legs", source.account_id, total).await?;
+    }
+
+    reserve_idempotency_key(&state, business_id, &payload.idempotency_key).await?;
+
+    let process_transfer = with_retry(|| async {
+        let mut tx = state
+            .pool
+            .begin()
+            .await
+            .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+        let (_, response) = execute_multi_leg_transfer(
+            &mut tx,
+            business_id,
+            &legs,
+            &payload.idempotency_key,
+            request_id.as_str(),
+        )
+        .await?;
+
+        store_idempotency_key(&mut tx, business_id, &payload.idempotency_key, &response).await?;
+
+        tx.commit()
+            .await
+            .map_err(AppError::database("Failed to commit transaction"))?;
+
+        state
+            .metrics
+            .record_money_movement("multi_leg", &response.currency, response.amount);
+
+        Ok(Json(response))
+    });
+
+    match process_transfer.await {
+        Ok(response) => Ok(response),
+        Err(err) => {
+            let _ = fail_idempotency_key(&state, business_id, &payload.idempotency_key).await;
+            Err(err)
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use crate::error::AppError;
+use crate::extract::{Json, Path, Query};
+use crate::middlewares::request_id::RequestId;
+use crate::models::{
+    CreateScheduledTransferRequest, ListResponse, PageParams, ScheduledTransferResponse,
+};
+use crate::services::accounts::{
+    check_idempotency_cache, create_webhook_event, fail_idempotency_key, reserve_idempotency_key,
+    store_idempotency_key,
+};
+use crate::services::pagination::Page;
+use crate::services::schedules::{
+    cancel_schedule, check_schedule_accounts, create_schedule, get_schedule, list_schedules,
+    validate_schedule_input,
+};
+use crate::state::AppState;
+use axum::extract::{Extension, State};
+use sqlx::types::Uuid;
+use tracing::instrument;
+
+fn parse_schedule_id(schedule_id: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(schedule_id)
+        .map_err(|_| AppError::InvalidRequest("Invalid scheduled transfer ID format"))
+}
+
+/// Schedules a one-off or recurring transfer. No money moves until the first
+/// occurrence is due.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn create_schedule_handler(
+    State(state): State<AppState>,
+    Extension(business_id): Extension<Uuid>,
+    Extension(request_id): Extension<RequestId>,
+    Json(payload): Json<CreateScheduledTransferRequest>,
+) -> Result<Json<ScheduledTransferResponse>, AppError> {
+    let schedule = validate_schedule_input(&payload)?;
+
+    if let Some(mut cached_response) = check_idempotency_cache::<ScheduledTransferResponse>(
+        &state,
+        business_id,
+        &payload.idempotency_key,
+    )
+    .await?
+    {
+        state
+            .metrics
+            .idempotency_cache_hits
+            .with_label_values(&["scheduled_transfer"])
+            .inc();
+        cached_response.cached = Some(true);
+        return Ok(Json(cached_response));
+    }
+
+    reserve_idempotency_key(&state, business_id, &payload.idempotency_key).await?;
+
+    let process_schedule = async {
+        check_schedule_accounts(&state, business_id, &schedule).await?;
+
+        let mut tx = state
+            .pool
+            .begin()
+            .await
+            .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+        let response =
+            create_schedule(&mut tx, business_id, &schedule, &payload.idempotency_key).await?;
+
+        create_webhook_event(
+            &mut tx,
+            business_id,
+            "scheduled_transfer.created",
+            &response,
+            request_id.as_str(),
+        )
+        .await?;
+
+        store_idempotency_key(&mut tx, business_id, &payload.idempotency_key, &response).await?;
+
+        tx.commit()
+            .await
+            .map_err(|_| AppError::Internal("Failed to commit transaction"))?;
+
+        Ok(Json(response))
+    };
+
+    match process_schedule.await {
+        Ok(response) => Ok(response),
+        Err(err) => {
+            let _ = fail_idempotency_key(&state, business_id, &payload.idempotency_key).await;
+            Err(err)
+        }
+    }
+}
+
+#[instrument(skip_all, fields(%business_id))]
+pub async fn list_schedules_handler(
+    State(state): State<AppState>,
+    Extension(business_id): Extension<Uuid>,
+    Query(page_params): Query<PageParams>,
+) -> Result<Json<ListResponse<ScheduledTransferResponse>>, AppError> {
+    let page = Page::from_params(&page_params)?;
+    let schedules = list_schedules(&state, business_id, &page).await?;
+    Ok(Json(schedules))
+}
+
+/// A schedule with the outcome of each occurrence run so far.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn get_schedule_handler(
+    State(state): State<AppState>,
+    Extension(business_id): Extension<Uuid>,
+    Path(schedule_id): Path<String>,
+) -> Result<Json<ScheduledTransferResponse>, AppError> {
+    let schedule_id = parse_schedule_id(&schedule_id)?;
+    let schedule = get_schedule(&state, business_id, schedule_id).await?;
+    Ok(Json(schedule))
+}
+
+/// Stops a schedule; occurrences that already ran are not affected.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn cancel_schedule_handler(
+    State(state): State<AppState>,
+    Extension(business_id): Extension<Uuid>,
+    Path(schedule_id): Path<String>,
+) -> Result<Json<ScheduledTransferResponse>, AppError> {
+    let schedule_id = parse_schedule_id(&schedule_id)?;
+    let schedule = cancel_schedule(&state, business_id, schedule_id).await?;
+    Ok(Json(schedule))
+}
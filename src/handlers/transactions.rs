@@ -0,0 +1,280 @@
+use crate::error::{AppError, FieldError};
+use crate::extract::{Json, Path, Query};
+use crate::middlewares::request_id::RequestId;
+use crate::models::{
+    IdempotencyKeyResponse, ListResponse, PageParams, ReversalResponse, ReverseTransactionRequest,
+    TransactionListQuery, TransactionResponse, TransactionStatusChange, TransferResponse,
+};
+use crate::services::accounts::{
+    check_idempotency_cache, create_webhook_event, fail_idempotency_key, reserve_idempotency_key,
+    store_idempotency_key,
+};
+use crate::services::ledger::{self, JournalEntry, LedgerAccount};
+use crate::services::limits::check_currency_limits;
+use crate::services::pagination::Page;
+use crate::services::retry::with_retry;
+use crate::services::settlement::{fail_transfer, lock_pending_transfer, settle_transfer};
+use crate::services::transactions::{
+    create_reversal_record, get_idempotency_key, get_transaction, list_status_changes,
+    list_transactions, lock_reversible_transfer,
+};
+use crate::state::AppState;
+use axum::extract::{Extension, State};
+use sqlx::types::Uuid;
+use tracing::instrument;
+
+#[instrument(skip_all, fields(%business_id))]
+pub async fn list_transactions_handler(
+    State(state): State<AppState>,
+    Extension(business_id): Extension<Uuid>,
+    Query(filters): Query<TransactionListQuery>,
+    Query(page_params): Query<PageParams>,
+) -> Result<Json<ListResponse<TransactionResponse>>, AppError> {
+    let page = Page::from_params(&page_params)?;
+    let response = list_transactions(&state, business_id, &filters, &page).await?;
+    Ok(Json(response))
+}
+
+#[instrument(skip_all, fields(%business_id))]
+pub async fn get_transaction_handler(
+    State(state): State<AppState>,
+    Extension(business_id): Extension<Uuid>,
+    Path(transaction_id): Path<String>,
+) -> Result<Json<TransactionResponse>, AppError> {
+    let transaction_id = Uuid::parse_str(&transaction_id)
+        .map_err(|_| AppError::InvalidRequest("Invalid transaction_id format"))?;
+    let response = get_transaction(&state, business_id, transaction_id).await?;
+    Ok(Json(response))
+}
+
+#[instrument(skip_all, fields(%business_id))]
+pub async fn get_transaction_history_handler(
+    State(state): State<AppState>,
+    Extension(business_id): Extension<Uuid>,
+    Path(transaction_id): Path<String>,
+) -> Result<Json<Vec<TransactionStatusChange>>, AppError> {
+    let transaction_id = Uuid::parse_str(&transaction_id)
+        .map_err(|_| AppError::InvalidRequest("Invalid transaction_id format"))?;
+    let response = list_status_changes(&state, business_id, transaction_id).await?;
+    Ok(Json(response))
+}
+
+/// Settles one of the caller's pending transfers, moving the held funds.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn settle_transaction_handler(
+    State(state): State<AppState>,
+    Extension(business_id): Extension<Uuid>,
+    Extension(request_id): Extension<RequestId>,
+    Path(transaction_id): Path<String>,
+) -> Result<Json<TransferResponse>, AppError> {
+    let transaction_id = Uuid::parse_str(&transaction_id)
+        .map_err(|_| AppError::InvalidRequest("Invalid transaction_id format"))?;
+
+    let response = with_retry(|| async {
+        let mut tx = state
+            .pool
+            .begin()
+            .await
+            .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+        let transfer = lock_pending_transfer(&mut tx, business_id, transaction_id).await?;
+        let response = settle_transfer(&mut tx, &transfer, request_id.as_str()).await?;
+
+        tx.commit()
+            .await
+            .map_err(AppError::database("Failed to commit transaction"))?;
+        Ok(response)
+    })
+    .await?;
+
+    state
+        .metrics
+        .record_money_movement("transfer", &response.currency, response.amount);
+
+    Ok(Json(response))
+}
+
+/// Fails one of the caller's pending transfers, releasing the held funds.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn fail_transaction_handler(
+    State(state): State<AppState>,
+    Extension(business_id): Extension<Uuid>,
+    Extension(request_id): Extension<RequestId>,
+    Path(transaction_id): Path<String>,
+) -> Result<Json<TransferResponse>, AppError> {
+    let transaction_id = Uuid::parse_str(&transaction_id)
+        .map_err(|_| AppError::InvalidRequest("Invalid transaction_id format"))?;
+
+    let response = with_retry(|| async {
+        let mut tx = state
+            .pool
+            .begin()
+            .await
+            .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+        let transfer = lock_pending_transfer(&mut tx, business_id, transaction_id).await?;
+        let response = fail_transfer(&mut tx, &transfer, request_id.as_str()).await?;
+
+        tx.commit()
+            .await
+            .map_err(AppError::database("Failed to commit transaction"))?;
+        Ok(response)
+    })
+    .await?;
+
+    Ok(Json(response))
+}
+
+#[instrument(skip_all, fields(%business_id))]
+pub async fn get_idempotency_key_handler(
+    State(state): State<AppState>,
+    Extension(business_id): Extension<Uuid>,
+    Path(idempotency_key): Path<String>,
+) -> Result<Json<IdempotencyKeyResponse>, AppError> {
+    let response = get_idempotency_key(&state, business_id, &idempotency_key).await?;
+    Ok(Json(response))
+}
+
+/// Returns all or part of a completed transfer to its source account. Only
+/// the business that received the transfer may reverse it.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn reverse_transaction_handler(
+    State(state): State<AppState>,
+    Extension(business_id): Extension<Uuid>,
+    Extension(request_id): Extension<RequestId>,
+    Path(transaction_id): Path<String>,
+    Json(payload): Json<ReverseTransactionRequest>,
+) -> Result<Json<ReversalResponse>, AppError> {
+    let transaction_id = Uuid::parse_str(&transaction_id)
+        .map_err(|_| AppError::InvalidRequest("Invalid transaction_id format"))?;
+    if payload.amount.is_some_and(|amount| amount <= 0) {
+        return Err(AppError::InvalidRequest("Amount must be positive"));
+    }
+
+    if let Some(mut cached_response) =
+        check_idempotency_cache::<ReversalResponse>(&state, business_id, &payload.idempotency_key)
+            .await?
+    {
+        state
+            .metrics
+            .idempotency_cache_hits
+            .with_label_values(&["reverse"])
+            .inc();
+        cached_response.cached = Some(true);
+        return Ok(Json(cached_response));
+    }
+
+    reserve_idempotency_key(&state, business_id, &payload.idempotency_key).await?;
+
+    let process_reversal = with_retry(|| async {
+        let mut tx = state
+            .pool
+            .begin()
+            .await
+            .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+        let transfer = lock_reversible_transfer(&mut tx, business_id, transaction_id).await?;
+
+        if transfer.remaining == 0 {
+            return Err(AppError::Conflict("Transfer is already fully reversed"));
+        }
+        let amount = payload.amount.unwrap_or(transfer.remaining);
+        if amount > transfer.remaining {
+            return Err(AppError::Validation(vec![FieldError {
+                field: "amount".to_string(),
+                message: format!(
+                    "must be at most {}, the amount not yet reversed",
+                    transfer.remaining
+                ),
+            }]));
+        }
+        check_currency_limits(
+            &state.config.amount_limits,
+            "amount",
+            &transfer.currency,
+            amount,
+        )?;
+        if transfer.available < amount {
+            return Err(AppError::InsufficientFunds {
+                available: transfer.available,
+                required: amount,
+            });
+        }
+
+        let reversal_id = create_reversal_record(
+            &mut tx,
+            business_id,
+            transaction_id,
+            &transfer,
+            amount,
+            &payload.idempotency_key,
+            request_id.as_str(),
+        )
+        .await?;
+
+        ledger::post(
+            &mut tx,
+            &JournalEntry::movement(
+                "reversal",
+                &transfer.currency,
+                Some(reversal_id),
+                LedgerAccount::Customer(transfer.to_account_id),
+                LedgerAccount::Customer(transfer.from_account_id),
+                amount,
+            ),
+        )
+        .await?;
+
+        let response = ReversalResponse {
+            transaction_id: reversal_id.to_string(),
+            parent_transaction_id: transaction_id.to_string(),
+            from_account_id: transfer.to_account_id.to_string(),
+            to_account_id: transfer.from_account_id.to_string(),
+            amount,
+            currency: transfer.currency.clone(),
+            status: "success".to_string(),
+            remaining_amount: transfer.remaining - amount,
+            cached: None,
+        };
+
+        // Both sides of a cross-business transfer hear about the reversal
+        create_webhook_event(
+            &mut tx,
+            business_id,
+            "transfer.reversed",
+            &response,
+            request_id.as_str(),
+        )
+        .await?;
+        if transfer.initiated_by != business_id {
+            create_webhook_event(
+                &mut tx,
+                transfer.initiated_by,
+                "transfer.reversed",
+                &response,
+                request_id.as_str(),
+            )
+            .await?;
+        }
+
+        store_idempotency_key(&mut tx, business_id, &payload.idempotency_key, &response).await?;
+
+        tx.commit()
+            .await
+            .map_err(AppError::database("Failed to commit transaction"))?;
+
+        state
+            .metrics
+            .record_money_movement("reversal", &response.currency, response.amount);
+
+        Ok(Json(response))
+    });
+
+    match process_reversal.await {
+        Ok(response) => Ok(response),
+        Err(err) => {
+            let _ = fail_idempotency_key(&state, business_id, &payload.idempotency_key).await;
+            Err(err)
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use crate::error::AppError;
+use crate::extract::Json;
+use crate::models::{
+    AuthContext, CredentialKind, RecoveryCodesResponse, TotpCodeRequest, TotpEnrollmentResponse,
+};
+use crate::services::two_factor::{begin_enrollment, confirm_enrollment, disable};
+use crate::state::AppState;
+use axum::extract::{Extension, State};
+use serde_json::{json, Value};
+use sqlx::types::Uuid;
+use tracing::instrument;
+
+/// Two-factor settings belong to a person, so they can only be changed from a
+/// dashboard session, never with an API key.
+fn session_user(auth: &AuthContext) -> Result<Uuid, AppError> {
+    match (auth.credential, auth.user_id) {
+        (CredentialKind::AccessToken, Some(user_id)) => Ok(user_id),
+        _ => Err(AppError::Forbidden(
+            "Two-factor settings require a dashboard session",
+        )),
+    }
+}
+
+#[instrument(skip_all)]
+pub async fn enroll_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<TotpEnrollmentResponse>, AppError> {
+    let user_id = session_user(&auth)?;
+    let response = begin_enrollment(&state, user_id).await?;
+    Ok(Json(response))
+}
+
+#[instrument(skip_all)]
+pub async fn confirm_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Json(payload): Json<TotpCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, AppError> {
+    let user_id = session_user(&auth)?;
+    let response = confirm_enrollment(&state, user_id, &payload.totp_code).await?;
+    Ok(Json(response))
+}
+
+#[instrument(skip_all)]
+pub async fn disable_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Json(payload): Json<TotpCodeRequest>,
+) -> Result<Json<Value>, AppError> {
+    let user_id = session_user(&auth)?;
+    disable(&state, user_id, &payload.totp_code).await?;
+    Ok(Json(json!({ "status": "two_factor_disabled" })))
+}
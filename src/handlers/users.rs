@@ -0,0 +1,37 @@
+use crate::error::AppError;
+use crate::extract::Json;
+use crate::models::{
+    AcceptInvitationRequest, AuthContext, InvitationResponse, InviteUserRequest, UserResponse,
+};
+use crate::services::users::{accept_invitation, invite_user, list_users};
+use crate::state::AppState;
+use axum::extract::{Extension, State};
+use tracing::instrument;
+
+#[instrument(skip_all)]
+pub async fn invite_user_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Json(payload): Json<InviteUserRequest>,
+) -> Result<Json<InvitationResponse>, AppError> {
+    let response = invite_user(&state, &auth, payload).await?;
+    Ok(Json(response))
+}
+
+#[instrument(skip_all)]
+pub async fn accept_invitation_handler(
+    State(state): State<AppState>,
+    Json(payload): Json<AcceptInvitationRequest>,
+) -> Result<Json<UserResponse>, AppError> {
+    let response = accept_invitation(&state, payload).await?;
+    Ok(Json(response))
+}
+
+#[instrument(skip_all)]
+pub async fn list_users_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<Vec<UserResponse>>, AppError> {
+    let response = list_users(&state, auth.business_id).await?;
+    Ok(Json(response))
+}
@@ -1,27 +1,28 @@
+use crate::error::AppError;
+use crate::extract::Json;
 use crate::services::webhooks::{
     list_webhooks, register_webhook, RegisterWebhookRequest, WebhookEndpointResponse,
 };
 use crate::state::AppState;
-use axum::{
-    extract::{Extension, State},
-    Json,
-};
-use serde_json::Value;
+use axum::extract::{Extension, State};
 use sqlx::types::Uuid;
+use tracing::instrument;
 
+#[instrument(skip_all, fields(%business_id))]
 pub async fn register_webhook_handler(
     State(state): State<AppState>,
     Extension(business_id): Extension<Uuid>,
     Json(payload): Json<RegisterWebhookRequest>,
-) -> Result<Json<WebhookEndpointResponse>, Json<Value>> {
+) -> Result<Json<WebhookEndpointResponse>, AppError> {
     let response = register_webhook(&state, business_id, payload).await?;
     Ok(Json(response))
 }
 
+#[instrument(skip_all, fields(%business_id))]
 pub async fn list_webhooks_handler(
     State(state): State<AppState>,
     Extension(business_id): Extension<Uuid>,
-) -> Result<Json<Vec<WebhookEndpointResponse>>, Json<Value>> {
+) -> Result<Json<Vec<WebhookEndpointResponse>>, AppError> {
     let response = list_webhooks(&state, business_id).await?;
     Ok(Json(response))
 }
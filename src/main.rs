@@ -1,33 +1,117 @@
-use sqlx::postgres::PgPoolOptions;
+use sqlx::postgres::{PgPool, PgPoolOptions};
 use std::net::SocketAddr;
+use std::sync::Arc;
 use std::time::Duration;
 
-use dodointerview::{create_router, AppState};
+use dodointerview::services::ledger;
+use dodointerview::services::notifier::notifier_from_config;
+use dodointerview::telemetry;
+use dodointerview::{create_router, AppState, Config};
 
 #[tokio::main]
 async fn main() {
     dotenvy::dotenv().ok();
 
+    match std::env::args().nth(1).as_deref() {
+        None | Some("serve") => serve().await,
+        Some("verify-ledger") => verify_ledger().await,
+        Some(other) => {
+            eprintln!(
+                "Unknown command '{}'. Usage: dodointerview [serve | verify-ledger]",
+                other
+            );
+            std::process::exit(2);
+        }
+    }
+}
+
+async fn connect() -> PgPool {
     let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
 
-    let pool = PgPoolOptions::new()
+    PgPoolOptions::new()
         .max_connections(5)
         .acquire_timeout(Duration::from_secs(3))
         .connect(&db_url)
         .await
-        .expect("Failed to connect to database");
+        .expect("Failed to connect to database")
+}
+
+/// Prints the ledger verification report as JSON and exits non-zero when it
+/// finds discrepancies, so it can gate deploys or run from cron.
+async fn verify_ledger() {
+    let pool = connect().await;
+    match ledger::verify(&pool).await {
+        Ok(report) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&report).expect("report serializes")
+            );
+            if !report.ok {
+                std::process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("Ledger verification failed: {:?}", e);
+            std::process::exit(2);
+        }
+    }
+}
 
-    let state = AppState { pool };
+async fn serve() {
+    let config = Config::from_env();
+    let _telemetry = telemetry::init(&config);
+
+    let pool = connect().await;
+
+    let state = AppState {
+        pool,
+        notifier: notifier_from_config(&config),
+        metrics: Default::default(),
+        webhook_heartbeat: Default::default(),
+        config: Arc::new(config),
+    };
 
     let app = create_router(state.clone()).with_state(state.clone());
 
+    if let Some(interval) = state.config.ledger_verify_interval {
+        tokio::spawn(ledger::run_verification_schedule(state.clone(), interval));
+        tracing::info!(
+            interval_secs = interval.as_secs(),
+            "Ledger verification scheduled"
+        );
+    }
+
+    tokio::spawn(dodointerview::services::holds::run_expiry(state.clone()));
+    tokio::spawn(dodointerview::services::batches::run_batch_worker(
+        state.clone(),
+    ));
+    tokio::spawn(dodointerview::services::schedules::run_scheduler(
+        state.clone(),
+    ));
+
+    if let Some(delay) = state.config.settlement_delay {
+        tokio::spawn(dodointerview::services::settlement::run_settlement(
+            state.clone(),
+            delay,
+        ));
+        tracing::info!(
+            delay_secs = delay.as_secs(),
+            "Automatic settlement of pending transfers enabled"
+        );
+    }
+
     // Spawn background worker for webhooks
     tokio::spawn(dodointerview::services::webhooks::process_webhooks(state));
 
     let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
-    println!("Server running at http://{}", addr);
-    println!("Webhooks service enabled");
+    tracing::info!(%addr, "Server running");
+    tracing::info!("Webhooks service enabled");
 
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
@@ -0,0 +1,195 @@
+use crate::models::LedgerVerificationReport;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+
+/// Prometheus collectors for the service. Each `AppState` owns its own
+/// registry, so tests can build independent apps without clashing.
+pub struct Metrics {
+    registry: Registry,
+    /// Requests by method, matched route and status code.
+    pub http_requests: IntCounterVec,
+    pub http_request_duration: HistogramVec,
+    /// Completed transfers, credits and debits by type and currency.
+    pub money_movements: IntCounterVec,
+    /// Sum of moved amounts, in minor units, by type and currency.
+    pub money_movement_amount: IntCounterVec,
+    /// Responses served from the idempotency cache, by endpoint.
+    pub idempotency_cache_hits: IntCounterVec,
+    pub rate_limit_rejections: IntCounter,
+    /// Pool connections by state (`idle`, `in_use`); refreshed on scrape.
+    pub db_pool_connections: IntGaugeVec,
+    /// Webhook events by status; refreshed on scrape.
+    pub webhook_events: IntGaugeVec,
+    /// Delivery attempts by outcome (`delivered`, `retrying`, `failed`).
+    pub webhook_deliveries: IntCounterVec,
+    pub webhook_delivery_duration: HistogramVec,
+    /// Ledger verification runs by outcome (`ok`, `discrepancies`, `error`).
+    pub ledger_verifications: IntCounterVec,
+    /// Problems found by the last completed verification.
+    pub ledger_discrepancies: IntGauge,
+    pub ledger_last_verified: IntGauge,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry =
+            Registry::new_custom(Some("dodo".to_string()), None).expect("valid metrics namespace");
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests handled"),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP request latency"),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let money_movements = IntCounterVec::new(
+            Opts::new(
+                "money_movements_total",
+                "Completed transfers, credits and debits",
+            ),
+            &["type", "currency"],
+        )
+        .unwrap();
+        let money_movement_amount = IntCounterVec::new(
+            Opts::new(
+                "money_movement_amount_total",
+                "Amount moved in minor currency units",
+            ),
+            &["type", "currency"],
+        )
+        .unwrap();
+        let idempotency_cache_hits = IntCounterVec::new(
+            Opts::new(
+                "idempotency_cache_hits_total",
+                "Responses replayed from the idempotency cache",
+            ),
+            &["endpoint"],
+        )
+        .unwrap();
+        let rate_limit_rejections = IntCounter::new(
+            "rate_limit_rejections_total",
+            "Requests rejected by the rate limiter",
+        )
+        .unwrap();
+        let db_pool_connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "Database pool connections"),
+            &["state"],
+        )
+        .unwrap();
+        let webhook_events = IntGaugeVec::new(
+            Opts::new("webhook_events", "Webhook events by delivery status"),
+            &["status"],
+        )
+        .unwrap();
+        let webhook_deliveries = IntCounterVec::new(
+            Opts::new("webhook_deliveries_total", "Webhook delivery attempts"),
+            &["outcome"],
+        )
+        .unwrap();
+        let webhook_delivery_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "webhook_delivery_duration_seconds",
+                "Time spent on a single webhook delivery attempt",
+            ),
+            &["outcome"],
+        )
+        .unwrap();
+
+        let ledger_verifications = IntCounterVec::new(
+            Opts::new("ledger_verifications_total", "Ledger verification runs"),
+            &["outcome"],
+        )
+        .unwrap();
+        let ledger_discrepancies = IntGauge::new(
+            "ledger_discrepancies",
+            "Balance mismatches, unbalanced journal entries and unposted transactions found by the last ledger verification",
+        )
+        .unwrap();
+        let ledger_last_verified = IntGauge::new(
+            "ledger_last_verified_timestamp_seconds",
+            "Unix time of the last completed ledger verification",
+        )
+        .unwrap();
+
+        for collector in [
+            Box::new(http_requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(http_request_duration.clone()),
+            Box::new(money_movements.clone()),
+            Box::new(money_movement_amount.clone()),
+            Box::new(idempotency_cache_hits.clone()),
+            Box::new(rate_limit_rejections.clone()),
+            Box::new(db_pool_connections.clone()),
+            Box::new(webhook_events.clone()),
+            Box::new(webhook_deliveries.clone()),
+            Box::new(webhook_delivery_duration.clone()),
+            Box::new(ledger_verifications.clone()),
+            Box::new(ledger_discrepancies.clone()),
+            Box::new(ledger_last_verified.clone()),
+        ] {
+            registry
+                .register(collector)
+                .expect("metric registered once");
+        }
+
+        Self {
+            registry,
+            http_requests,
+            http_request_duration,
+            money_movements,
+            money_movement_amount,
+            idempotency_cache_hits,
+            rate_limit_rejections,
+            db_pool_connections,
+            webhook_events,
+            webhook_deliveries,
+            webhook_delivery_duration,
+            ledger_verifications,
+            ledger_discrepancies,
+            ledger_last_verified,
+        }
+    }
+
+    /// Records a committed transfer, credit or debit.
+    pub fn record_money_movement(&self, kind: &str, currency: &str, amount: i64) {
+        self.money_movements
+            .with_label_values(&[kind, currency])
+            .inc();
+        self.money_movement_amount
+            .with_label_values(&[kind, currency])
+            .inc_by(amount.max(0) as u64);
+    }
+
+    /// Records the outcome of a completed ledger verification.
+    pub fn record_ledger_verification(&self, report: &LedgerVerificationReport) {
+        let problems = report.discrepancies.len()
+            + report.unbalanced_journal_entries.len()
+            + report.unposted_transactions.len();
+        let outcome = if report.ok { "ok" } else { "discrepancies" };
+        self.ledger_verifications
+            .with_label_values(&[outcome])
+            .inc();
+        self.ledger_discrepancies.set(problems as i64);
+        self.ledger_last_verified
+            .set(chrono::Utc::now().timestamp());
+    }
+
+    /// Renders every collector in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("text encoding cannot fail");
+        String::from_utf8(buffer).expect("metrics are valid UTF-8")
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
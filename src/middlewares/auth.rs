@@ -1,12 +1,14 @@
+use crate::error::AppError;
+use crate::models::{AuthContext, CredentialKind, Permission, UserRole};
+use crate::services::auth::{hash_token, verify_access_token, API_KEY_PREFIX};
 use crate::state::AppState;
 use axum::{
-    extract::{Request, State},
+    extract::{ConnectInfo, Request, State},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
 };
-use hex;
-use sha2::{Digest, Sha256};
 use sqlx::Row;
+use std::net::SocketAddr;
 use tower_governor::{errors::GovernorError, key_extractor::KeyExtractor};
 
 #[derive(Clone, Copy, Eq, PartialEq)]
@@ -16,59 +18,124 @@ impl KeyExtractor for ApiKeyExtractor {
     type Key = String;
 
     fn extract<B>(&self, req: &Request<B>) -> Result<Self::Key, GovernorError> {
-        req.headers()
+        // Requests without credentials share one bucket and are then rejected
+        // by auth_middleware with a 401, rather than failing here with a 500.
+        Ok(req
+            .headers()
             .get("Authorization")
             .and_then(|value| value.to_str().ok())
-            .map(|s| s.to_string())
-            .ok_or(GovernorError::UnableToExtractKey)
+            .unwrap_or("anonymous")
+            .to_string())
     }
 }
 
+/// Keys the `/auth` rate limit by the client's address, as those requests
+/// carry no credential yet.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct ClientIpExtractor;
+
+impl KeyExtractor for ClientIpExtractor {
+    type Key = String;
+
+    fn extract<B>(&self, req: &Request<B>) -> Result<Self::Key, GovernorError> {
+        // Without connection info, as when the router is called directly,
+        // clients share one bucket rather than failing here with a 500
+        Ok(req
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string())
+            .unwrap_or_else(|| "unknown".to_string()))
+    }
+}
+
+/// Guards platform operator routes with the configured `ADMIN_TOKEN`, sent as
+/// `Authorization: Bearer <token>`. Hashes are compared so the check does not
+/// leak the token through timing.
+pub async fn admin_auth_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(admin_token) = state.config.admin_token.as_deref() else {
+        return AppError::Unauthorized.into_response();
+    };
+
+    let credential = request
+        .headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or("");
+
+    if credential.is_empty() || hash_token(credential) != hash_token(admin_token) {
+        return AppError::Unauthorized.into_response();
+    }
+
+    next.run(request).await
+}
+
+/// Accepts either an API key (`sk_live_...`) or a dashboard access token,
+/// optionally prefixed with `Bearer `.
 pub async fn auth_middleware(
     State(state): State<AppState>,
     mut request: Request,
     next: Next,
 ) -> Response {
-    let api_key = request
+    let credential = request
         .headers()
         .get("Authorization")
         .and_then(|value| value.to_str().ok())
         .unwrap_or("");
+    let credential = credential.strip_prefix("Bearer ").unwrap_or(credential);
 
-    if api_key.is_empty() {
-        return Response::builder()
-            .status(401)
-            .body("Unauthorized".into())
-            .unwrap_or_default();
+    if credential.is_empty() {
+        return AppError::Unauthorized.into_response();
     }
 
-    let mut hasher = Sha256::new();
-    hasher.update(api_key.as_bytes());
-    let key_hash = hex::encode(hasher.finalize());
+    if !credential.starts_with(API_KEY_PREFIX) {
+        return match verify_access_token(&state, credential) {
+            Some(auth) => {
+                request.extensions_mut().insert(auth.business_id);
+                request.extensions_mut().insert(auth);
+                next.run(request).await
+            }
+            None => AppError::Unauthorized.into_response(),
+        };
+    }
 
     let row = match sqlx::query(
-        "SELECT business_id FROM api_keys WHERE key_hash = $1 AND is_active = true",
+        "SELECT business_id, role, created_by FROM api_keys WHERE key_hash = $1 AND is_active = true",
     )
-    .bind(&key_hash)
+    .bind(hash_token(credential))
     .fetch_optional(&state.pool)
     .await
     {
         Ok(Some(row)) => row,
-        Ok(None) => {
-            return Response::builder()
-                .status(401)
-                .body("Unauthorized".into())
-                .unwrap_or_default();
-        }
-        Err(_) => {
-            return Response::builder()
-                .status(500)
-                .body("Internal Server Error".into())
-                .unwrap_or_default();
-        }
+        Ok(None) => return AppError::Unauthorized.into_response(),
+        Err(_) => return AppError::Internal("Failed to verify API key").into_response(),
     };
 
-    let business_id: sqlx::types::Uuid = row.get("business_id");
-    request.extensions_mut().insert(business_id);
+    let auth = AuthContext {
+        business_id: row.get("business_id"),
+        user_id: row.get("created_by"),
+        role: row.get::<UserRole, _>("role"),
+        credential: CredentialKind::ApiKey,
+    };
+    request.extensions_mut().insert(auth.business_id);
+    request.extensions_mut().insert(auth);
     next.run(request).await
 }
+
+/// Route layer rejecting callers whose role lacks `permission`. Must run
+/// inside `auth_middleware`.
+pub async fn require_permission(
+    State(permission): State<Permission>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match request.extensions().get::<AuthContext>() {
+        Some(auth) if permission.allows(auth.role) => next.run(request).await,
+        Some(_) => AppError::Forbidden("Insufficient permissions").into_response(),
+        None => AppError::Unauthorized.into_response(),
+    }
+}
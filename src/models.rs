@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use sqlx::types::Uuid;
 
 #[derive(Deserialize)]
 pub struct CreateAccountRequest {
@@ -7,6 +8,7 @@ pub struct CreateAccountRequest {
 
 #[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, sqlx::Type)]
 #[sqlx(type_name = "idempotency_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
 pub enum IdempotencyStatus {
     Pending,
     Success,
@@ -21,10 +23,87 @@ pub enum WebhookEventStatus {
     Failed,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "user_role", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum UserRole {
+    Owner,
+    Admin,
+    Developer,
+    Viewer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    ManageUsers,
+    ManageApiKeys,
+    ManageWebhooks,
+    ManageAccounts,
+    MoveMoney,
+}
+
+impl Permission {
+    pub fn allows(self, role: UserRole) -> bool {
+        match self {
+            Permission::ManageUsers | Permission::ManageAccounts | Permission::MoveMoney => {
+                matches!(role, UserRole::Owner | UserRole::Admin)
+            }
+            Permission::ManageApiKeys | Permission::ManageWebhooks => {
+                matches!(
+                    role,
+                    UserRole::Owner | UserRole::Admin | UserRole::Developer
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredentialKind {
+    ApiKey,
+    AccessToken,
+}
+
+/// Identity attached to a request by `auth_middleware`.
+#[derive(Debug, Clone, Copy)]
+pub struct AuthContext {
+    pub business_id: Uuid,
+    /// `None` for API keys minted before keys were tied to a user.
+    pub user_id: Option<Uuid>,
+    pub role: UserRole,
+    pub credential: CredentialKind,
+}
+
 #[derive(Deserialize)]
 pub struct GetAccountsQuery {
     pub currency: Option<String>,
-    pub business_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Pagination parameters accepted by list endpoints, next to their filters.
+#[derive(Deserialize, Default)]
+pub struct PageParams {
+    pub limit: Option<i64>,
+    /// `next_cursor` from the previous page.
+    pub starting_after: Option<String>,
+    /// Direction of the `created_at, id` ordering; ascending by default.
+    pub order: Option<SortOrder>,
+}
+
+/// Envelope for paginated list responses.
+#[derive(Serialize)]
+pub struct ListResponse<T> {
+    pub data: Vec<T>,
+    pub has_more: bool,
+    /// Pass as `starting_after` to fetch the next page; `null` on the last page.
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -33,6 +112,10 @@ pub struct TransferRequest {
     pub to_account_id: String,
     pub amount: i64,
     pub idempotency_key: String,
+    /// Create the transfer as `pending`: funds are held on the source account
+    /// and only move once it is settled.
+    #[serde(default)]
+    pub pending: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -47,6 +130,186 @@ pub struct TransferResponse {
     pub cached: Option<bool>,
 }
 
+/// One account's side of a multi-leg transfer: negative amounts leave the
+/// account, positive ones arrive.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TransferLeg {
+    pub account_id: String,
+    pub amount: i64,
+}
+
+#[derive(Deserialize)]
+pub struct MultiLegTransferRequest {
+    pub legs: Vec<TransferLeg>,
+    pub idempotency_key: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MultiLegTransferResponse {
+    pub transaction_id: String,
+    /// Total leaving the source accounts.
+    pub amount: i64,
+    pub currency: String,
+    pub status: String,
+    pub legs: Vec<TransferLeg>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cached: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchMode {
+    /// Every transfer commits, or none does.
+    #[default]
+    AllOrNothing,
+    /// Each transfer commits on its own; failures are reported per item.
+    BestEffort,
+}
+
+impl BatchMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::AllOrNothing => "all_or_nothing",
+            Self::BestEffort => "best_effort",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct BatchTransferRequest {
+    pub idempotency_key: String,
+    #[serde(default)]
+    pub mode: BatchMode,
+    pub transfers: Vec<BatchTransferItem>,
+}
+
+#[derive(Deserialize)]
+pub struct BatchTransferItem {
+    pub from_account_id: String,
+    pub to_account_id: String,
+    pub amount: i64,
+}
+
+/// Why one transfer of a batch or schedule failed, in the shape of the error
+/// the equivalent API call would have returned.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ItemError {
+    pub code: String,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BatchItemResult {
+    /// Position of the transfer in the request, from 0.
+    pub index: i32,
+    pub from_account_id: String,
+    pub to_account_id: String,
+    pub amount: i64,
+    /// `queued`, `succeeded`, `failed`, or `skipped` when an all-or-nothing
+    /// batch was rolled back because of another item.
+    pub status: String,
+    pub transaction_id: Option<String>,
+    pub error: Option<ItemError>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BatchResponse {
+    pub id: String,
+    pub mode: BatchMode,
+    /// `queued`, `processing`, `completed` or `failed`.
+    pub status: String,
+    pub total_count: i64,
+    pub succeeded_count: i64,
+    pub failed_count: i64,
+    pub items: Vec<BatchItemResult>,
+    pub created_at: String,
+    pub completed_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cached: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum RecurrenceInterval {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl RecurrenceInterval {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+            Self::Monthly => "monthly",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "daily" => Some(Self::Daily),
+            "weekly" => Some(Self::Weekly),
+            "monthly" => Some(Self::Monthly),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Recurrence {
+    pub interval: RecurrenceInterval,
+    /// RFC 3339; no occurrence is scheduled after it.
+    pub end_at: Option<String>,
+    /// Total number of occurrences, including the first.
+    pub count: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateScheduledTransferRequest {
+    pub from_account_id: String,
+    pub to_account_id: String,
+    pub amount: i64,
+    /// RFC 3339 time of the first (or only) occurrence.
+    pub start_at: String,
+    /// Omit for a one-off transfer.
+    pub recurrence: Option<Recurrence>,
+    pub idempotency_key: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ScheduledTransferRun {
+    /// 1 for the first occurrence.
+    pub occurrence: i32,
+    pub scheduled_for: String,
+    /// `succeeded` or `failed`.
+    pub status: String,
+    pub transaction_id: Option<String>,
+    pub error: Option<ItemError>,
+    pub created_at: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ScheduledTransferResponse {
+    pub id: String,
+    pub from_account_id: String,
+    pub to_account_id: String,
+    pub amount: i64,
+    /// `active`, `completed` or `cancelled`.
+    pub status: String,
+    pub start_at: String,
+    pub recurrence: Option<Recurrence>,
+    /// `null` once no further occurrence is due.
+    pub next_run_at: Option<String>,
+    /// Occurrences run so far, whether they succeeded or failed.
+    pub occurrences: i32,
+    pub created_at: String,
+    /// Only included when fetching a single schedule.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runs: Option<Vec<ScheduledTransferRun>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cached: Option<bool>,
+}
+
 #[derive(Serialize)]
 pub struct AccountResponse {
     pub id: String,
@@ -54,6 +317,42 @@ pub struct AccountResponse {
     pub business_name: Option<String>,
     pub business_email: String,
     pub balance: i64,
+    /// `balance` minus active holds; what can be moved right now.
+    pub available_balance: i64,
+    pub currency: String,
+}
+
+/// An account as seen by its owner.
+#[derive(Serialize)]
+pub struct AccountDetailResponse {
+    pub id: String,
+    pub business_id: String,
+    pub balance: i64,
+    pub available_balance: i64,
+    pub currency: String,
+    pub status: String,
+    pub created_at: String,
+}
+
+#[derive(Deserialize)]
+pub struct BalanceQuery {
+    /// RFC 3339 timestamp; defaults to now.
+    pub as_of: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct BalanceResponse {
+    pub account_id: String,
+    pub balance: i64,
+    pub currency: String,
+    pub as_of: String,
+}
+
+/// What any authenticated business may learn about another business's
+/// account before paying it.
+#[derive(Serialize)]
+pub struct AccountLookupResponse {
+    pub id: String,
     pub currency: String,
 }
 
@@ -77,3 +376,204 @@ pub struct CreditDebitResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cached: Option<bool>,
 }
+
+#[derive(Deserialize)]
+pub struct InviteUserRequest {
+    pub email: String,
+    pub role: UserRole,
+}
+
+#[derive(Serialize)]
+pub struct InvitationResponse {
+    pub id: String,
+    pub business_id: String,
+    pub email: String,
+    pub role: UserRole,
+    pub invite_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct AcceptInvitationRequest {
+    pub invite_token: String,
+    pub name: String,
+    pub password: String,
+}
+
+#[derive(Serialize)]
+pub struct UserResponse {
+    pub id: String,
+    pub business_id: String,
+    pub email: String,
+    pub name: Option<String>,
+    pub role: UserRole,
+}
+
+#[derive(Serialize)]
+pub struct TotpEnrollmentResponse {
+    pub secret: String,
+    pub otpauth_url: String,
+}
+
+#[derive(Deserialize)]
+pub struct TotpCodeRequest {
+    pub totp_code: String,
+}
+
+#[derive(Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+/// Filters for `GET /transactions`; all optional and combined with AND.
+#[derive(Deserialize, Default)]
+pub struct TransactionListQuery {
+    /// Only transactions moving money into or out of this account.
+    pub account_id: Option<String>,
+    #[serde(rename = "type")]
+    pub transaction_type: Option<String>,
+    pub status: Option<String>,
+    /// RFC 3339, inclusive.
+    pub created_after: Option<String>,
+    /// RFC 3339, exclusive.
+    pub created_before: Option<String>,
+    pub min_amount: Option<i64>,
+    pub max_amount: Option<i64>,
+    /// Only reversals of this transfer.
+    pub parent_transaction_id: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TransactionResponse {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub transaction_type: String,
+    pub status: String,
+    pub amount: i64,
+    pub currency: Option<String>,
+    pub from_account_id: Option<String>,
+    pub to_account_id: Option<String>,
+    /// Only shown to the business that initiated the transaction.
+    pub idempotency_key: Option<String>,
+    /// For reversals, the transfer they return funds from.
+    pub parent_transaction_id: Option<String>,
+    pub created_at: String,
+    /// Only included for multi-leg transfers fetched by ID.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub legs: Option<Vec<TransferLeg>>,
+}
+
+/// One entry in a pending transfer's status history.
+#[derive(Serialize)]
+pub struct TransactionStatusChange {
+    /// `null` for the entry recording the transfer's creation.
+    pub from_status: Option<String>,
+    pub to_status: String,
+    /// The API request or settlement run that made the change.
+    pub request_id: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Deserialize)]
+pub struct ReverseTransactionRequest {
+    /// Defaults to everything not yet reversed.
+    pub amount: Option<i64>,
+    pub idempotency_key: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ReversalResponse {
+    pub transaction_id: String,
+    pub parent_transaction_id: String,
+    /// The original destination account, now paying the funds back.
+    pub from_account_id: String,
+    pub to_account_id: String,
+    pub amount: i64,
+    pub currency: String,
+    pub status: String,
+    /// How much of the original transfer can still be reversed.
+    pub remaining_amount: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cached: Option<bool>,
+}
+
+/// Outcome of an earlier request, looked up by the idempotency key it was sent with.
+#[derive(Serialize)]
+pub struct IdempotencyKeyResponse {
+    pub idempotency_key: String,
+    pub status: IdempotencyStatus,
+    /// The response originally returned; only present once the request succeeded.
+    pub response: Option<serde_json::Value>,
+    pub created_at: Option<String>,
+}
+
+/// Result of recomputing every account's balance from the ledger.
+#[derive(Serialize)]
+pub struct LedgerVerificationReport {
+    /// True when nothing below needs attention.
+    pub ok: bool,
+    pub checked_at: String,
+    pub accounts_checked: i64,
+    pub currencies: Vec<CurrencyVerification>,
+    /// Accounts whose cached balance differs from their postings.
+    pub discrepancies: Vec<BalanceDiscrepancy>,
+    /// Journal entries whose debits and credits differ.
+    pub unbalanced_journal_entries: Vec<String>,
+    /// Successful transactions with no journal entry.
+    pub unposted_transactions: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct CurrencyVerification {
+    pub currency: String,
+    pub accounts: i64,
+    pub balance: i128,
+    pub ledger_balance: i128,
+    pub difference: i128,
+    pub discrepancies: i64,
+}
+
+#[derive(Serialize)]
+pub struct BalanceDiscrepancy {
+    pub account_id: String,
+    pub business_id: String,
+    pub currency: String,
+    pub balance: i128,
+    pub ledger_balance: i128,
+    /// `balance - ledger_balance`.
+    pub difference: i128,
+}
+
+#[derive(Deserialize)]
+pub struct CreateHoldRequest {
+    pub account_id: String,
+    pub amount: i64,
+    /// Defaults to 7 days; at most 30 days.
+    pub expires_in_seconds: Option<i64>,
+    pub idempotency_key: String,
+}
+
+#[derive(Deserialize)]
+pub struct CaptureHoldRequest {
+    /// Defaults to the full hold; any remainder is released.
+    pub amount: Option<i64>,
+    /// Capture as a transfer to this account; without it the capture is a debit.
+    pub to_account_id: Option<String>,
+    pub idempotency_key: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HoldResponse {
+    pub id: String,
+    pub account_id: String,
+    pub amount: i64,
+    pub currency: String,
+    /// `active`, `captured`, `voided` or `expired`.
+    pub status: String,
+    pub captured_amount: Option<i64>,
+    /// The transfer or debit created by the capture.
+    pub transaction_id: Option<String>,
+    pub expires_at: String,
+    pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cached: Option<bool>,
+}
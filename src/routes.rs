@@ -1,13 +1,25 @@
-use crate::handlers::{accounts, auth, health};
-use crate::middlewares::auth::{auth_middleware, ApiKeyExtractor};
+use crate::error::AppError;
+use crate::handlers::{
+    accounts, admin, auth, batches, health, holds, metrics, multi_leg, schedules, transactions,
+    two_factor, users,
+};
+use crate::middlewares::auth::{
+    admin_auth_middleware, auth_middleware, require_permission, ApiKeyExtractor, ClientIpExtractor,
+};
+use crate::middlewares::metrics::track_http_metrics;
+use crate::middlewares::request_id::{make_request_span, request_id_middleware, REQUEST_ID_HEADER};
+use crate::models::Permission;
 use crate::state::AppState;
 use axum::{
     middleware::{self},
+    response::IntoResponse,
     routing::{get, post},
     Router,
 };
-use tower_governor::{governor::GovernorConfigBuilder, GovernorLayer};
+use tower_governor::{errors::GovernorError, governor::GovernorConfigBuilder, GovernorLayer};
 use tower_http::cors::{Any, CorsLayer};
+use tower_http::trace::{DefaultOnResponse, TraceLayer};
+use tracing::Level;
 
 pub fn create_router(state: AppState) -> Router<AppState> {
     // Rate limiting configuration
@@ -18,18 +30,164 @@ pub fn create_router(state: AppState) -> Router<AppState> {
         .finish()
         .unwrap();
 
-    let governor_layer = GovernorLayer::new(governor_conf);
+    // Sign-in, signup and password resets carry no credential, so they are
+    // limited per client address
+    let auth_governor_conf = GovernorConfigBuilder::default()
+        .per_second(10)
+        .burst_size(20)
+        .key_extractor(ClientIpExtractor)
+        .finish()
+        .unwrap();
+
+    let rate_limit_metrics = state.metrics.clone();
+    let rate_limit_error = move |error: GovernorError| {
+        let error = match error {
+            GovernorError::TooManyRequests { wait_time, .. } => {
+                rate_limit_metrics.rate_limit_rejections.inc();
+                AppError::RateLimited {
+                    retry_after_seconds: wait_time.max(1) as i64,
+                }
+            }
+            _ => AppError::Internal("Rate limiter failed"),
+        };
+        error.into_response()
+    };
+    let governor_layer = GovernorLayer::new(governor_conf).error_handler(rate_limit_error.clone());
+    let auth_governor_layer =
+        GovernorLayer::new(auth_governor_conf).error_handler(rate_limit_error);
+
+    // Role checks run inside auth_middleware, which attaches the caller's role
+    let requires =
+        |permission: Permission| middleware::from_fn_with_state(permission, require_permission);
 
     // Auth routes
     let auth_routes = Router::new()
         .route("/generate-api-key", post(auth::generate_api_key))
-        .route("/signup", post(auth::signup));
+        .route("/signup", post(auth::signup))
+        .route("/login", post(auth::login))
+        .route("/refresh", post(auth::refresh))
+        .route("/logout", post(auth::logout))
+        .route("/forgot-password", post(auth::forgot_password))
+        .route("/reset-password", post(auth::reset_password_handler))
+        .route("/accept-invite", post(users::accept_invitation_handler))
+        .layer(auth_governor_layer);
+
+    // Protected two-factor routes
+    let protected_two_factor_routes = Router::new()
+        .route("/enroll", post(two_factor::enroll_handler))
+        .route("/confirm", post(two_factor::confirm_handler))
+        .route("/disable", post(two_factor::disable_handler))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ))
+        .layer(governor_layer.clone());
 
     // Protected accounts routes
     let protected_accounts_routes = Router::new()
-        .route("/create", post(accounts::create_account))
-        .route("/transfer", post(accounts::transfer))
-        .route("/credit-debit", post(accounts::credit_debit))
+        .route("/", get(accounts::get_accounts))
+        .route("/lookup/{id}", get(accounts::lookup_account))
+        .route("/{id}", get(accounts::get_account_handler))
+        .route("/{id}/balance", get(accounts::get_balance_handler))
+        .route(
+            "/create",
+            post(accounts::create_account).layer(requires(Permission::ManageAccounts)),
+        )
+        .route(
+            "/transfer",
+            post(accounts::transfer).layer(requires(Permission::MoveMoney)),
+        )
+        .route(
+            "/credit-debit",
+            post(accounts::credit_debit).layer(requires(Permission::MoveMoney)),
+        )
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ))
+        .layer(governor_layer.clone());
+
+    // Protected transaction history routes
+    let protected_transactions_routes = Router::new()
+        .route("/", get(transactions::list_transactions_handler))
+        .route("/{id}", get(transactions::get_transaction_handler))
+        .route(
+            "/idempotency-keys/{key}",
+            get(transactions::get_idempotency_key_handler),
+        )
+        .route(
+            "/{id}/reverse",
+            post(transactions::reverse_transaction_handler).layer(requires(Permission::MoveMoney)),
+        )
+        .route(
+            "/{id}/history",
+            get(transactions::get_transaction_history_handler),
+        )
+        .route(
+            "/{id}/settle",
+            post(transactions::settle_transaction_handler).layer(requires(Permission::MoveMoney)),
+        )
+        .route(
+            "/{id}/fail",
+            post(transactions::fail_transaction_handler).layer(requires(Permission::MoveMoney)),
+        )
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ))
+        .layer(governor_layer.clone());
+
+    // Protected batch and multi-leg transfer routes
+    let protected_transfers_routes = Router::new()
+        .route(
+            "/batch",
+            post(batches::create_batch_handler).layer(requires(Permission::MoveMoney)),
+        )
+        .route("/batch/{id}", get(batches::get_batch_handler))
+        .route(
+            "/multi-leg",
+            post(multi_leg::multi_leg_transfer_handler).layer(requires(Permission::MoveMoney)),
+        )
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ))
+        .layer(governor_layer.clone());
+
+    // Protected scheduled transfer routes
+    let protected_schedules_routes = Router::new()
+        .route(
+            "/",
+            get(schedules::list_schedules_handler).merge(
+                post(schedules::create_schedule_handler).layer(requires(Permission::MoveMoney)),
+            ),
+        )
+        .route("/{id}", get(schedules::get_schedule_handler))
+        .route(
+            "/{id}/cancel",
+            post(schedules::cancel_schedule_handler).layer(requires(Permission::MoveMoney)),
+        )
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ))
+        .layer(governor_layer.clone());
+
+    // Protected authorization hold routes
+    let protected_holds_routes = Router::new()
+        .route(
+            "/",
+            post(holds::create_hold_handler).layer(requires(Permission::MoveMoney)),
+        )
+        .route("/{id}", get(holds::get_hold_handler))
+        .route(
+            "/{id}/capture",
+            post(holds::capture_hold_handler).layer(requires(Permission::MoveMoney)),
+        )
+        .route(
+            "/{id}/void",
+            post(holds::void_hold_handler).layer(requires(Permission::MoveMoney)),
+        )
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth_middleware,
@@ -40,7 +198,8 @@ pub fn create_router(state: AppState) -> Router<AppState> {
     let protected_webhooks_routes = Router::new()
         .route(
             "/register",
-            post(crate::handlers::webhooks::register_webhook_handler),
+            post(crate::handlers::webhooks::register_webhook_handler)
+                .layer(requires(Permission::ManageWebhooks)),
         )
         .route(
             "/list",
@@ -50,23 +209,70 @@ pub fn create_router(state: AppState) -> Router<AppState> {
             state.clone(),
             auth_middleware,
         ))
+        .layer(governor_layer.clone());
+
+    // Protected user management routes
+    let protected_users_routes = Router::new()
+        .route(
+            "/invite",
+            post(users::invite_user_handler).layer(requires(Permission::ManageUsers)),
+        )
+        .route("/list", get(users::list_users_handler))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ))
         .layer(governor_layer);
 
-    // Public accounts routes
-    let public_accounts_routes = Router::new().route("/", get(accounts::get_accounts));
+    // Platform operator routes, outside any business
+    let admin_routes = Router::new()
+        .route("/ledger/verify", get(admin::verify_ledger_handler))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            admin_auth_middleware,
+        ));
 
     Router::new()
         .route("/", get(health::health_check))
+        .route("/healthz", get(health::liveness))
+        .route("/readyz", get(health::readiness))
+        .route("/version", get(health::build_info))
+        // Business-sensitive totals, so scrapers authenticate like operators
+        .route(
+            "/metrics",
+            get(metrics::metrics_handler).layer(middleware::from_fn_with_state(
+                state.clone(),
+                admin_auth_middleware,
+            )),
+        )
+        .nest("/accounts", protected_accounts_routes)
+        .nest("/transactions", protected_transactions_routes)
+        .nest("/transfers", protected_transfers_routes)
+        .nest("/holds", protected_holds_routes)
+        .nest("/scheduled-transfers", protected_schedules_routes)
         .nest(
-            "/accounts",
-            public_accounts_routes.merge(protected_accounts_routes),
+            "/auth",
+            auth_routes.nest("/2fa", protected_two_factor_routes),
         )
-        .nest("/auth", auth_routes)
         .nest("/webhooks", protected_webhooks_routes)
+        .nest("/users", protected_users_routes)
+        .nest("/admin", admin_routes)
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            track_http_metrics,
+        ))
         .layer(
             CorsLayer::new()
                 .allow_origin(Any)
                 .allow_methods(Any)
-                .allow_headers(Any),
+                .allow_headers(Any)
+                .expose_headers([REQUEST_ID_HEADER.clone()]),
+        )
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(make_request_span)
+                .on_response(DefaultOnResponse::new().level(Level::INFO)),
         )
+        // Outermost, so every response carries the request ID
+        .layer(middleware::from_fn(request_id_middleware))
 }
@@ -1,29 +1,46 @@
-use crate::models::{CreditDebitRequest, IdempotencyStatus, TransferRequest};
+use crate::config::AmountLimits;
+use crate::error::AppError;
+use crate::models::{
+    AccountDetailResponse, BalanceResponse, CreditDebitRequest, CreditDebitResponse,
+    IdempotencyStatus, TransferRequest, TransferResponse,
+};
+use crate::services::holds::HELD_AMOUNT;
+use crate::services::ledger::{self, JournalEntry, LedgerAccount, SystemAccount};
+use crate::services::limits::check_currency_limits;
+use crate::services::timestamps::to_rfc3339;
 use crate::state::AppState;
-use axum::Json;
+use chrono::NaiveDateTime;
 use serde::{de::DeserializeOwned, Serialize};
-use serde_json::{json, Value};
 use sqlx::{types::Uuid, Row};
+use std::collections::HashMap;
+use tracing::instrument;
 
-pub fn validate_transfer_input(payload: &TransferRequest) -> Result<(Uuid, Uuid), Json<Value>> {
+pub fn validate_transfer_input(payload: &TransferRequest) -> Result<(Uuid, Uuid), AppError> {
     if payload.amount <= 0 {
-        return Err(Json(json!({ "error": "Amount must be positive" })));
+        return Err(AppError::InvalidRequest("Amount must be positive"));
     }
 
     let from_account_id = Uuid::parse_str(&payload.from_account_id)
-        .map_err(|_| Json(json!({ "error": "Invalid from_account_id format" })))?;
+        .map_err(|_| AppError::InvalidRequest("Invalid from_account_id format"))?;
 
     let to_account_id = Uuid::parse_str(&payload.to_account_id)
-        .map_err(|_| Json(json!({ "error": "Invalid to_account_id format" })))?;
+        .map_err(|_| AppError::InvalidRequest("Invalid to_account_id format"))?;
+
+    if from_account_id == to_account_id {
+        return Err(AppError::InvalidRequest(
+            "Cannot transfer to the same account",
+        ));
+    }
 
     Ok((from_account_id, to_account_id))
 }
 
+#[instrument(skip_all, fields(%business_id))]
 pub async fn check_idempotency_cache<T>(
     state: &AppState,
     business_id: Uuid,
     idempotency_key: &str,
-) -> Result<Option<T>, Json<Value>>
+) -> Result<Option<T>, AppError>
 where
     T: DeserializeOwned + Clone,
 {
@@ -50,11 +67,12 @@ where
     Ok(None)
 }
 
+#[instrument(skip_all, fields(%business_id))]
 pub async fn reserve_idempotency_key(
     state: &AppState,
     business_id: Uuid,
     idempotency_key: &str,
-) -> Result<(), Json<Value>> {
+) -> Result<(), AppError> {
     // Try to insert as pending.
     // If it exists:
     //   if status_code is success (200) -> Return conflict/check cache (handler should have checked cache first)
@@ -88,19 +106,14 @@ pub async fn reserve_idempotency_key(
                 .fetch_optional(&state.pool)
                 .await;
 
-                match existing {
-                    Ok(Some(row)) => {
-                        let status: IdempotencyStatus = row.get("status");
-                        if status == IdempotencyStatus::Pending {
-                            return Err(Json(json!({ "error": "Operation in progress" })));
-                        } else if status == IdempotencyStatus::Success {
-                            // Should have been caught by cache check, but ok.
-                            return Err(Json(
-                                json!({ "error": "Operation already completed successfully" }),
-                            ));
-                        }
+                if let Ok(Some(row)) = existing {
+                    let status: IdempotencyStatus = row.get("status");
+                    if status == IdempotencyStatus::Pending {
+                        return Err(AppError::IdempotencyInProgress);
+                    } else if status == IdempotencyStatus::Success {
+                        // Should have been caught by cache check, but ok.
+                        return Err(AppError::IdempotencyCompleted);
                     }
-                    _ => {}
                 }
                 // If we are here, something weird happened or it was retriable but update didn't run?
                 // Actually, the DO UPDATE WHERE clause prevents update if it's Success or Pending.
@@ -108,22 +121,54 @@ pub async fn reserve_idempotency_key(
             }
             Ok(())
         }
-        Err(_) => Err(Json(
-            json!({ "error": "Failed to reserve idempotency key" }),
-        )),
+        Err(_) => Err(AppError::Internal("Failed to reserve idempotency key")),
     }
 }
 
+/// Locks the given accounts in ID order. Every lock on more than one account
+/// is taken in this order, so two transactions touching the same accounts
+/// wait for each other instead of deadlocking. Unknown IDs are skipped.
+#[instrument(skip_all)]
+pub async fn lock_accounts(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    account_ids: &[Uuid],
+) -> Result<(), AppError> {
+    let mut account_ids = account_ids.to_vec();
+    account_ids.sort();
+    account_ids.dedup();
+    sqlx::query("SELECT id FROM accounts WHERE id = ANY($1) ORDER BY id FOR UPDATE")
+        .bind(&account_ids)
+        .fetch_all(&mut **tx)
+        .await
+        .map_err(AppError::database("Failed to lock accounts"))?;
+    Ok(())
+}
+
+/// Locks both accounts of a transfer and checks ownership, currencies, the
+/// amount limits and the source's available funds. Returns the currency and
+/// the source's balance.
+#[instrument(skip_all, fields(%business_id))]
 pub async fn fetch_and_validate_accounts(
     tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    limits: &HashMap<String, AmountLimits>,
     from_account_id: Uuid,
     to_account_id: Uuid,
     business_id: Uuid,
     amount: i64,
-) -> Result<(String, i64), Json<Value>> {
-    let from_account = sqlx::query(
-        "SELECT id, business_id, balance, currency FROM accounts WHERE id = $1 AND business_id = $2 FOR UPDATE"
-    )
+) -> Result<(String, i64), AppError> {
+    // Also reached by paths that did not validate a transfer request, such as
+    // capturing a hold
+    if from_account_id == to_account_id {
+        return Err(AppError::InvalidRequest(
+            "Cannot transfer to the same account",
+        ));
+    }
+    lock_accounts(tx, &[from_account_id, to_account_id]).await?;
+
+    let from_account = sqlx::query(&format!(
+        "SELECT a.id, a.business_id, a.balance, a.currency, a.balance - {HELD_AMOUNT} AS available_balance
+         FROM accounts a WHERE a.id = $1 AND a.business_id = $2"
+    ))
     .bind(from_account_id)
     .bind(business_id)
     .fetch_optional(&mut **tx)
@@ -132,79 +177,53 @@ pub async fn fetch_and_validate_accounts(
     let from_account = match from_account {
         Ok(Some(row)) => row,
         Ok(None) => {
-            return Err(Json(
-                json!({ "error": "Source account not found or does not belong to this business" }),
+            return Err(AppError::NotFound(
+                "Source account not found or does not belong to this business",
             ));
         }
         Err(_) => {
-            return Err(Json(json!({ "error": "Failed to fetch source account" })));
+            return Err(AppError::Internal("Failed to fetch source account"));
         }
     };
 
-    let to_account = sqlx::query(
-        "SELECT id, business_id, balance, currency FROM accounts WHERE id = $1 FOR UPDATE",
-    )
-    .bind(to_account_id)
-    .fetch_optional(&mut **tx)
-    .await;
+    let to_account =
+        sqlx::query("SELECT id, business_id, balance, currency FROM accounts WHERE id = $1")
+            .bind(to_account_id)
+            .fetch_optional(&mut **tx)
+            .await;
 
     let to_account = match to_account {
         Ok(Some(row)) => row,
         Ok(None) => {
-            return Err(Json(json!({ "error": "Destination account not found" })));
+            return Err(AppError::NotFound("Destination account not found"));
         }
         Err(_) => {
-            return Err(Json(
-                json!({ "error": "Failed to fetch destination account" }),
-            ));
+            return Err(AppError::Internal("Failed to fetch destination account"));
         }
     };
     let from_currency: String = from_account.get("currency");
     let to_currency: String = to_account.get("currency");
 
     if from_currency != to_currency {
-        return Err(Json(json!({
-            "error": "Currency mismatch",
-            "from_currency": from_currency,
-            "to_currency": to_currency
-        })));
+        return Err(AppError::CurrencyMismatch {
+            from_currency,
+            to_currency,
+        });
     }
-
-    let from_balance: i64 = from_account.get("balance");
-    if from_balance < amount {
-        return Err(Json(json!({
-            "error": "Insufficient balance",
-            "available": from_balance,
-            "required": amount
-        })));
+    check_currency_limits(limits, "amount", &from_currency, amount)?;
+
+    let available: i64 = from_account.get("available_balance");
+    if available < amount {
+        return Err(AppError::InsufficientFunds {
+            available,
+            required: amount,
+        });
     }
 
-    Ok((from_currency, from_balance))
-}
-
-pub async fn execute_balance_transfer(
-    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
-    from_account_id: Uuid,
-    to_account_id: Uuid,
-    amount: i64,
-) -> Result<(), Json<Value>> {
-    sqlx::query("UPDATE accounts SET balance = balance - $1 WHERE id = $2")
-        .bind(amount)
-        .bind(from_account_id)
-        .execute(&mut **tx)
-        .await
-        .map_err(|_| Json(json!({ "error": "Failed to debit source account" })))?;
-
-    sqlx::query("UPDATE accounts SET balance = balance + $1 WHERE id = $2")
-        .bind(amount)
-        .bind(to_account_id)
-        .execute(&mut **tx)
-        .await
-        .map_err(|_| Json(json!({ "error": "Failed to credit destination account" })))?;
-
-    Ok(())
+    Ok((from_currency, from_account.get("balance")))
 }
 
+#[instrument(skip_all, fields(%business_id))]
 pub async fn create_transaction_record(
     tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     business_id: Uuid,
@@ -212,34 +231,185 @@ pub async fn create_transaction_record(
     to_account_id: Uuid,
     amount: i64,
     idempotency_key: &str,
-) -> Result<Uuid, Json<Value>> {
+    request_id: &str,
+) -> Result<Uuid, AppError> {
     let transaction_result = sqlx::query(
-        "INSERT INTO transactions (business_id, from_account_id, to_account_id, amount, type, status, idempotency_key) 
-         VALUES ($1, $2, $3, $4, 'transfer', 'success', $5) RETURNING id"
+        "INSERT INTO transactions (business_id, from_account_id, to_account_id, amount, type, status, idempotency_key, request_id) 
+         VALUES ($1, $2, $3, $4, 'transfer', 'success', $5, $6) RETURNING id"
     )
     .bind(business_id)
     .bind(from_account_id)
     .bind(to_account_id)
     .bind(amount)
     .bind(idempotency_key)
+    .bind(request_id)
     .fetch_one(&mut **tx)
     .await;
 
     let transaction_id = transaction_result
         .map(|row| row.get::<Uuid, _>("id"))
-        .map_err(|_| Json(json!({ "error": "Failed to create transaction record" })))?;
+        .map_err(|_| AppError::Internal("Failed to create transaction record"))?;
 
     Ok(transaction_id)
 }
 
+/// Moves `amount` between two accounts: locks and checks them, records the
+/// transaction, posts it to the ledger and queues `transfer.created`. Shared by
+/// every path that makes an immediate transfer. Returns the transaction ID
+/// with the response.
+#[allow(clippy::too_many_arguments)]
+#[instrument(skip_all, fields(%business_id))]
+pub async fn execute_transfer(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    limits: &HashMap<String, AmountLimits>,
+    business_id: Uuid,
+    from_account_id: Uuid,
+    to_account_id: Uuid,
+    amount: i64,
+    idempotency_key: &str,
+    request_id: &str,
+) -> Result<(Uuid, TransferResponse), AppError> {
+    let (currency, _) = fetch_and_validate_accounts(
+        tx,
+        limits,
+        from_account_id,
+        to_account_id,
+        business_id,
+        amount,
+    )
+    .await?;
+
+    let transaction_id = create_transaction_record(
+        tx,
+        business_id,
+        from_account_id,
+        to_account_id,
+        amount,
+        idempotency_key,
+        request_id,
+    )
+    .await?;
+
+    ledger::post(
+        tx,
+        &JournalEntry::movement(
+            "transfer",
+            &currency,
+            Some(transaction_id),
+            LedgerAccount::Customer(from_account_id),
+            LedgerAccount::Customer(to_account_id),
+            amount,
+        ),
+    )
+    .await?;
+
+    let response = TransferResponse {
+        transaction_id: transaction_id.to_string(),
+        from_account_id: from_account_id.to_string(),
+        to_account_id: to_account_id.to_string(),
+        amount,
+        currency,
+        status: "success".to_string(),
+        cached: None,
+    };
+    create_webhook_event(tx, business_id, "transfer.created", &response, request_id).await?;
+
+    Ok((transaction_id, response))
+}
+
+/// Credits `amount` to, or debits it from, one of the caller's accounts. The
+/// money enters from, or leaves to, outside the platform. The amount must be
+/// within `limits`, and a debit may only use funds not held. Sends a
+/// `credit.created` or `debit.created` webhook.
+#[allow(clippy::too_many_arguments)]
+#[instrument(skip_all, fields(%business_id))]
+pub async fn execute_credit_debit(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    limits: &HashMap<String, AmountLimits>,
+    business_id: Uuid,
+    account_id: Uuid,
+    amount: i64,
+    transaction_type: &str,
+    idempotency_key: &str,
+    request_id: &str,
+) -> Result<(Uuid, CreditDebitResponse), AppError> {
+    let is_credit = transaction_type == "credit";
+    let (currency, current_balance, available) = fetch_account(tx, account_id, business_id).await?;
+    check_currency_limits(limits, "amount", &currency, amount)?;
+    if !is_credit && available < amount {
+        return Err(AppError::InsufficientFunds {
+            available,
+            required: amount,
+        });
+    }
+
+    let transaction_id = create_cd_record(
+        tx,
+        business_id,
+        account_id,
+        amount,
+        transaction_type,
+        idempotency_key,
+        request_id,
+    )
+    .await?;
+
+    let customer = LedgerAccount::Customer(account_id);
+    let (from, to) = if is_credit {
+        (
+            LedgerAccount::System(SystemAccount::ExternalFunding),
+            customer,
+        )
+    } else {
+        (
+            customer,
+            LedgerAccount::System(SystemAccount::ExternalPayouts),
+        )
+    };
+    // Rejects a credit the balance cannot hold, so the sum below fits
+    ledger::post(
+        tx,
+        &JournalEntry::movement(
+            transaction_type,
+            &currency,
+            Some(transaction_id),
+            from,
+            to,
+            amount,
+        ),
+    )
+    .await?;
+    let new_balance = if is_credit {
+        current_balance + amount
+    } else {
+        current_balance - amount
+    };
+
+    let response = CreditDebitResponse {
+        transaction_id: transaction_id.to_string(),
+        account_id: account_id.to_string(),
+        amount,
+        currency,
+        transaction_type: transaction_type.to_string(),
+        status: "success".to_string(),
+        new_balance,
+        cached: None,
+    };
+    let event_type = format!("{transaction_type}.created");
+    create_webhook_event(tx, business_id, &event_type, &response, request_id).await?;
+
+    Ok((transaction_id, response))
+}
+
+#[instrument(skip_all, fields(%business_id))]
 pub async fn store_idempotency_key<T: Serialize>(
     tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     business_id: Uuid,
     idempotency_key: &str,
     response: &T,
-) -> Result<(), Json<Value>> {
+) -> Result<(), AppError> {
     let response_json = serde_json::to_value(response)
-        .map_err(|_| Json(json!({ "error": "Failed to serialize response" })))?;
+        .map_err(|_| AppError::Internal("Failed to serialize response"))?;
 
     // Update the pending key to success
     sqlx::query(
@@ -252,16 +422,17 @@ pub async fn store_idempotency_key<T: Serialize>(
     .bind(idempotency_key)
     .execute(&mut **tx)
     .await
-    .map_err(|_| Json(json!({ "error": "Failed to update idempotency key" })))?;
+    .map_err(|_| AppError::Internal("Failed to update idempotency key"))?;
 
     Ok(())
 }
 
+#[instrument(skip_all, fields(%business_id))]
 pub async fn fail_idempotency_key(
     state: &AppState,
     business_id: Uuid,
     idempotency_key: &str,
-) -> Result<(), Json<Value>> {
+) -> Result<(), AppError> {
     sqlx::query(
         "UPDATE idempotency_keys 
          SET status = 'failed'::idempotency_status 
@@ -271,19 +442,21 @@ pub async fn fail_idempotency_key(
     .bind(idempotency_key)
     .execute(&state.pool)
     .await
-    .map_err(|_| Json(json!({ "error": "Failed to set idempotency key failure status" })))?;
+    .map_err(|_| AppError::Internal("Failed to set idempotency key failure status"))?;
 
     Ok(())
 }
 
+#[instrument(skip_all, fields(%business_id))]
 pub async fn create_webhook_event<T: Serialize>(
     tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     business_id: Uuid,
     event_type: &str,
     payload: &T,
-) -> Result<(), Json<Value>> {
+    request_id: &str,
+) -> Result<(), AppError> {
     let payload_json = serde_json::to_value(payload)
-        .map_err(|_| Json(json!({ "error": "Failed to serialize webhook payload" })))?;
+        .map_err(|_| AppError::Internal("Failed to serialize webhook payload"))?;
 
     // Find active endpoints
     let endpoints =
@@ -291,20 +464,21 @@ pub async fn create_webhook_event<T: Serialize>(
             .bind(business_id)
             .fetch_all(&mut **tx)
             .await
-            .map_err(|_| Json(json!({ "error": "Failed to fetch webhook endpoints" })))?;
+            .map_err(|_| AppError::Internal("Failed to fetch webhook endpoints"))?;
 
     for endpoint in endpoints {
         let endpoint_id: Uuid = endpoint.get("id");
         sqlx::query(
-            "INSERT INTO webhook_events (webhook_endpoint_id, event_type, payload) 
-             VALUES ($1, $2, $3)",
+            "INSERT INTO webhook_events (webhook_endpoint_id, event_type, payload, request_id) 
+             VALUES ($1, $2, $3, $4)",
         )
         .bind(endpoint_id)
         .bind(event_type)
         .bind(&payload_json)
+        .bind(request_id)
         .execute(&mut **tx)
         .await
-        .map_err(|_| Json(json!({ "error": "Failed to create webhook event" })))?;
+        .map_err(|_| AppError::Internal("Failed to create webhook event"))?;
     }
 
     Ok(())
@@ -312,29 +486,35 @@ pub async fn create_webhook_event<T: Serialize>(
 
 // Credit/Debit service functions
 
-pub fn validate_cd_input(payload: &CreditDebitRequest) -> Result<Uuid, Json<Value>> {
+pub fn validate_cd_input(payload: &CreditDebitRequest) -> Result<Uuid, AppError> {
     if payload.amount <= 0 {
-        return Err(Json(json!({ "error": "Amount must be positive" })));
+        return Err(AppError::InvalidRequest("Amount must be positive"));
     }
 
     if payload.transaction_type != "credit" && payload.transaction_type != "debit" {
-        return Err(Json(
-            json!({ "error": "Invalid transaction_type. Must be 'credit' or 'debit'" }),
+        return Err(AppError::InvalidRequest(
+            "Invalid transaction_type. Must be 'credit' or 'debit'",
         ));
     }
 
     Uuid::parse_str(&payload.account_id)
-        .map_err(|_| Json(json!({ "error": "Invalid account_id format" })))
+        .map_err(|_| AppError::InvalidRequest("Invalid account_id format"))
 }
 
+/// Locks one of the caller's accounts, returning its currency, balance and
+/// available balance.
+#[instrument(skip_all, fields(%business_id))]
 pub async fn fetch_account(
     tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     account_id: Uuid,
     business_id: Uuid,
-) -> Result<(String, i64), Json<Value>> {
-    let account = sqlx::query(
-        "SELECT id, business_id, balance, currency FROM accounts WHERE id = $1 AND business_id = $2 FOR UPDATE",
-    )
+) -> Result<(String, i64, i64), AppError> {
+    lock_accounts(tx, &[account_id]).await?;
+
+    let account = sqlx::query(&format!(
+        "SELECT a.id, a.business_id, a.balance, a.currency, a.balance - {HELD_AMOUNT} AS available_balance
+         FROM accounts a WHERE a.id = $1 AND a.business_id = $2"
+    ))
     .bind(account_id)
     .bind(business_id)
     .fetch_optional(&mut **tx)
@@ -344,39 +524,17 @@ pub async fn fetch_account(
         Ok(Some(row)) => {
             let currency: String = row.get("currency");
             let balance: i64 = row.get("balance");
-            Ok((currency, balance))
+            let available: i64 = row.get("available_balance");
+            Ok((currency, balance, available))
         }
-        Ok(None) => Err(Json(
-            json!({ "error": "Account not found or does not belong to this business" }),
+        Ok(None) => Err(AppError::NotFound(
+            "Account not found or does not belong to this business",
         )),
-        Err(_) => Err(Json(json!({ "error": "Failed to fetch account" }))),
-    }
-}
-
-pub async fn update_balance(
-    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
-    account_id: Uuid,
-    amount: i64,
-    is_credit: bool,
-) -> Result<i64, Json<Value>> {
-    let operator = if is_credit { "+" } else { "-" };
-    let query = format!(
-        "UPDATE accounts SET balance = balance {} $1 WHERE id = $2 RETURNING balance",
-        operator
-    );
-
-    let result = sqlx::query(&query)
-        .bind(amount)
-        .bind(account_id)
-        .fetch_one(&mut **tx)
-        .await;
-
-    match result {
-        Ok(row) => Ok(row.get("balance")),
-        Err(_) => Err(Json(json!({ "error": "Failed to update balance" }))),
+        Err(_) => Err(AppError::Internal("Failed to fetch account")),
     }
 }
 
+#[instrument(skip_all, fields(%business_id))]
 pub async fn create_cd_record(
     tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     business_id: Uuid,
@@ -384,7 +542,8 @@ pub async fn create_cd_record(
     amount: i64,
     transaction_type: &str,
     idempotency_key: &str,
-) -> Result<Uuid, Json<Value>> {
+    request_id: &str,
+) -> Result<Uuid, AppError> {
     // For credit: to_account_id = account_id, from_account_id = NULL
     // For debit: from_account_id = account_id, to_account_id = NULL
     let (from_id, to_id): (Option<Uuid>, Option<Uuid>) = if transaction_type == "credit" {
@@ -394,8 +553,8 @@ pub async fn create_cd_record(
     };
 
     let result = sqlx::query(
-        "INSERT INTO transactions (business_id, from_account_id, to_account_id, amount, type, status, idempotency_key) 
-         VALUES ($1, $2, $3, $4, $5, 'success', $6) RETURNING id",
+        "INSERT INTO transactions (business_id, from_account_id, to_account_id, amount, type, status, idempotency_key, request_id) 
+         VALUES ($1, $2, $3, $4, $5, 'success', $6, $7) RETURNING id",
     )
     .bind(business_id)
     .bind(from_id)
@@ -403,10 +562,81 @@ pub async fn create_cd_record(
     .bind(amount)
     .bind(transaction_type)
     .bind(idempotency_key)
+    .bind(request_id)
     .fetch_one(&mut **tx)
     .await;
 
     result
         .map(|row| row.get::<Uuid, _>("id"))
-        .map_err(|_| Json(json!({ "error": "Failed to create transaction record" })))
+        .map_err(|_| AppError::Internal("Failed to create transaction record"))
+}
+
+// Account read service functions
+
+#[instrument(skip_all, fields(%business_id))]
+pub async fn get_account(
+    state: &AppState,
+    business_id: Uuid,
+    account_id: Uuid,
+) -> Result<AccountDetailResponse, AppError> {
+    let row = sqlx::query(&format!(
+        "SELECT a.balance, a.currency, a.status, a.created_at, a.balance - {HELD_AMOUNT} AS available_balance
+         FROM accounts a WHERE a.id = $1 AND a.business_id = $2"
+    ))
+    .bind(account_id)
+    .bind(business_id)
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|_| AppError::Internal("Failed to fetch account"))?
+    .ok_or(AppError::NotFound("Account not found"))?;
+
+    Ok(AccountDetailResponse {
+        id: account_id.to_string(),
+        business_id: business_id.to_string(),
+        balance: row.get("balance"),
+        available_balance: row.get("available_balance"),
+        currency: row.get("currency"),
+        status: row.get("status"),
+        created_at: to_rfc3339(row.get("created_at")),
+    })
+}
+
+/// Balance at `as_of`: the sum of the account's ledger postings up to and
+/// including that moment.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn balance_as_of(
+    state: &AppState,
+    business_id: Uuid,
+    account_id: Uuid,
+    as_of: NaiveDateTime,
+) -> Result<BalanceResponse, AppError> {
+    let row = sqlx::query(
+        "SELECT a.currency, a.created_at,
+                COALESCE(SUM(CASE l.direction WHEN 'credit' THEN l.amount ELSE -l.amount END), 0)::BIGINT AS balance
+         FROM accounts a
+         LEFT JOIN ledger_entries l ON l.account_id = a.id AND l.created_at <= $3
+         WHERE a.id = $1 AND a.business_id = $2
+         GROUP BY a.id",
+    )
+    .bind(account_id)
+    .bind(business_id)
+    .bind(as_of)
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|_| AppError::Internal("Failed to compute balance"))?
+    .ok_or(AppError::NotFound("Account not found"))?;
+
+    let created_at: NaiveDateTime = row.get("created_at");
+    if as_of < created_at {
+        return Err(AppError::InvalidRequest(
+            "as_of is before the account was created",
+        ));
+    }
+
+    Ok(BalanceResponse {
+        account_id: account_id.to_string(),
+        balance: row.get("balance"),
+        currency: row.get("currency"),
+        as_of: to_rfc3339(as_of),
+    })
 }
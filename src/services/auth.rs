@@ -0,0 +1,579 @@
+use crate::error::AppError;
+use crate::models::{AuthContext, CredentialKind, UserRole};
+use crate::services::notifier::Notification;
+use crate::services::two_factor::{verify_second_factor, SecondFactor};
+use crate::services::validation::{normalize_email, Validator};
+use crate::state::AppState;
+use bcrypt::{hash, verify, DEFAULT_COST};
+use hex;
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{types::Uuid, Postgres, Row};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::instrument;
+
+pub const API_KEY_PREFIX: &str = "sk_live_";
+const REFRESH_TOKEN_PREFIX: &str = "rt_";
+const PASSWORD_RESET_PREFIX: &str = "prt_";
+const PASSWORD_RESET_TTL_MINUTES: i32 = 60;
+/// Minimum time between reset tokens sent to one user, so the endpoint cannot
+/// be used to flood an inbox.
+const PASSWORD_RESET_INTERVAL_MINUTES: i32 = 5;
+
+/// Failed logins allowed for an email before it is locked.
+const MAX_FAILED_ATTEMPTS: i32 = 5;
+/// First lockout duration; doubles with each further failure.
+const LOCKOUT_BASE_SECS: i64 = 30;
+const LOCKOUT_MAX_SECS: i64 = 60 * 60;
+/// Hash of a random password, checked when the email has no user so unknown
+/// emails take as long as wrong passwords.
+const DUMMY_PASSWORD_HASH: &str = "$2b$12$GAB2u6COwxOwTqiqlJRFlunuRbsK6mgBCfOdbbwABJA3zFbLRXLV2";
+
+/// Hashes a password with bcrypt on the blocking pool, so the hash does not
+/// stall a runtime worker.
+pub async fn hash_password(password: &str) -> Result<String, AppError> {
+    let password = password.to_string();
+    tokio::task::spawn_blocking(move || hash(password, DEFAULT_COST))
+        .await
+        .ok()
+        .and_then(Result::ok)
+        .ok_or(AppError::Internal("Failed to hash password"))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AccessClaims {
+    /// User id.
+    pub sub: String,
+    pub business_id: String,
+    pub role: UserRole,
+    pub iat: u64,
+    pub exp: u64,
+}
+
+/// A user whose email/password has been checked.
+#[derive(Debug, Clone, Copy)]
+pub struct AuthenticatedUser {
+    pub user_id: Uuid,
+    pub business_id: Uuid,
+    pub role: UserRole,
+}
+
+#[derive(Serialize)]
+pub struct SessionTokens {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: String,
+    pub expires_in: u64,
+}
+
+pub fn hash_token(token: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(token.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+pub fn generate_secret(prefix: &str) -> String {
+    format!(
+        "{}{}",
+        prefix,
+        hex::encode(rand::thread_rng().gen::<[u8; 32]>())
+    )
+}
+
+/// Checks email/password (and the second factor once two-factor is enabled),
+/// locking the email out with exponential backoff after repeated failures.
+/// Unknown emails are tracked and hashed against too, so neither lockout nor
+/// response time reveals which emails exist.
+#[instrument(skip_all)]
+pub async fn verify_credentials(
+    state: &AppState,
+    email: &str,
+    password: &str,
+    second_factor: &SecondFactor,
+) -> Result<AuthenticatedUser, AppError> {
+    let email = &normalize_email(email);
+
+    let mut tx = state
+        .pool
+        .begin()
+        .await
+        .map_err(|_| AppError::Internal("Database error"))?;
+
+    // Attempts on one email queue on its row, so concurrent guesses are each
+    // counted before the next one checks the lockout.
+    sqlx::query("INSERT INTO login_attempts (email) VALUES ($1) ON CONFLICT (email) DO NOTHING")
+        .bind(email)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| AppError::Internal("Database error"))?;
+
+    let locked_for: Option<i64> = sqlx::query_scalar(
+        "SELECT CASE WHEN locked_until > NOW() 
+                THEN EXTRACT(EPOCH FROM (locked_until - NOW()))::BIGINT END 
+         FROM login_attempts WHERE email = $1 FOR UPDATE",
+    )
+    .bind(email)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|_| AppError::Internal("Database error"))?;
+
+    if let Some(remaining) = locked_for {
+        return Err(AppError::AccountLocked {
+            retry_after_seconds: remaining.max(1),
+        });
+    }
+
+    let row = sqlx::query(
+        "SELECT id, business_id, role, password_hash, totp_enabled, totp_secret FROM users WHERE email = $1",
+    )
+    .bind(email)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| AppError::Internal("Database error"))?;
+
+    let password_hash = row
+        .as_ref()
+        .map(|row| row.get("password_hash"))
+        .unwrap_or_else(|| DUMMY_PASSWORD_HASH.to_string());
+    let password = password.to_string();
+    let password_matches =
+        tokio::task::spawn_blocking(move || verify(password, &password_hash).unwrap_or(false))
+            .await
+            .map_err(|_| AppError::Internal("Failed to verify password"))?;
+
+    let row = match row.filter(|_| password_matches) {
+        Some(row) => row,
+        None => {
+            record_failed_login(&mut tx, email).await?;
+            commit(tx).await?;
+            return Err(AppError::InvalidCredentials);
+        }
+    };
+
+    let user = AuthenticatedUser {
+        user_id: row.get("id"),
+        business_id: row.get("business_id"),
+        role: row.get("role"),
+    };
+
+    if row.get::<bool, _>("totp_enabled") {
+        let secret: String = row.get("totp_secret");
+        if !verify_second_factor(&mut *tx, user.user_id, &secret, email, second_factor).await? {
+            record_failed_login(&mut tx, email).await?;
+            commit(tx).await?;
+            return Err(AppError::InvalidTwoFactorCode);
+        }
+    }
+
+    clear_failed_logins(&mut tx, email).await?;
+    commit(tx).await?;
+    Ok(user)
+}
+
+async fn commit(tx: sqlx::Transaction<'_, Postgres>) -> Result<(), AppError> {
+    tx.commit()
+        .await
+        .map_err(|_| AppError::Internal("Database error"))
+}
+
+async fn record_failed_login(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    email: &str,
+) -> Result<(), AppError> {
+    // The counter starts over once an email has been quiet for an hour.
+    let row = sqlx::query(
+        "UPDATE login_attempts 
+         SET failed_count = CASE 
+                 WHEN last_failed_at < NOW() - INTERVAL '1 hour' THEN 1 
+                 ELSE failed_count + 1 
+             END, 
+             last_failed_at = NOW() 
+         WHERE email = $1 
+         RETURNING failed_count",
+    )
+    .bind(email)
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(|_| AppError::Internal("Database error"))?;
+
+    let failed_count: i32 = row.get("failed_count");
+    if failed_count < MAX_FAILED_ATTEMPTS {
+        return Ok(());
+    }
+
+    let exponent = (failed_count - MAX_FAILED_ATTEMPTS).min(16) as u32;
+    let lock_secs = (LOCKOUT_BASE_SECS << exponent).min(LOCKOUT_MAX_SECS);
+
+    sqlx::query(
+        "UPDATE login_attempts SET locked_until = NOW() + make_interval(secs => $1) WHERE email = $2",
+    )
+    .bind(lock_secs as f64)
+    .bind(email)
+    .execute(&mut **tx)
+    .await
+    .map_err(|_| AppError::Internal("Database error"))?;
+
+    Ok(())
+}
+
+async fn clear_failed_logins(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    email: &str,
+) -> Result<(), AppError> {
+    sqlx::query("DELETE FROM login_attempts WHERE email = $1")
+        .bind(email)
+        .execute(&mut **tx)
+        .await
+        .map_err(|_| AppError::Internal("Database error"))?;
+
+    Ok(())
+}
+
+/// Sends a single-use reset token to the user, if the email belongs to one.
+/// A user is sent at most one token per `PASSWORD_RESET_INTERVAL_MINUTES`
+/// while it is unused, and a failed send is only logged, so the outcome
+/// reveals nothing about whether the email is registered.
+#[instrument(skip_all)]
+pub async fn request_password_reset(state: &AppState, email: &str) -> Result<(), AppError> {
+    let email = &normalize_email(email);
+
+    let mut tx = state
+        .pool
+        .begin()
+        .await
+        .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+    // The row lock makes concurrent requests for one user take turns, so only
+    // one of them issues a token
+    let user = sqlx::query("SELECT id FROM users WHERE email = $1 FOR UPDATE")
+        .bind(email)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|_| AppError::Internal("Database error"))?;
+
+    let user_id: Uuid = match user {
+        Some(row) => row.get("id"),
+        None => return Ok(()),
+    };
+
+    let recently_sent: bool = sqlx::query_scalar(
+        "SELECT EXISTS (
+             SELECT 1 FROM password_reset_tokens
+             WHERE user_id = $1 AND used_at IS NULL
+               AND created_at > NOW() - make_interval(mins => $2)
+         )",
+    )
+    .bind(user_id)
+    .bind(PASSWORD_RESET_INTERVAL_MINUTES)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|_| AppError::Internal("Database error"))?;
+    if recently_sent {
+        return Ok(());
+    }
+
+    let reset_token = generate_secret(PASSWORD_RESET_PREFIX);
+
+    sqlx::query(
+        "INSERT INTO password_reset_tokens (user_id, token_hash, expires_at) 
+         VALUES ($1, $2, NOW() + make_interval(mins => $3))",
+    )
+    .bind(user_id)
+    .bind(hash_token(&reset_token))
+    .bind(PASSWORD_RESET_TTL_MINUTES)
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to create password reset token"))?;
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::Internal("Failed to commit transaction"))?;
+
+    let notification = Notification {
+        to: email.to_string(),
+        subject: "Reset your password".to_string(),
+        body: format!(
+            "Use this token to reset your password: {}\nIt expires in {} minutes.",
+            reset_token, PASSWORD_RESET_TTL_MINUTES
+        ),
+    };
+
+    if let Err(error) = state.notifier.send(&notification).await {
+        tracing::warn!(%user_id, error, "Failed to send password reset");
+    }
+    Ok(())
+}
+
+/// Consumes a reset token, sets the new password, ends existing sessions and
+/// lifts any lockout.
+#[instrument(skip_all)]
+pub async fn reset_password(
+    state: &AppState,
+    reset_token: &str,
+    new_password: &str,
+) -> Result<(), AppError> {
+    let mut validator = Validator::default();
+    validator.password("new_password", new_password);
+    validator.finish()?;
+
+    let password_hash = hash_password(new_password).await?;
+
+    let mut tx = state
+        .pool
+        .begin()
+        .await
+        .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+    let row = sqlx::query(
+        "UPDATE password_reset_tokens SET used_at = NOW() 
+         WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW() 
+         RETURNING user_id",
+    )
+    .bind(hash_token(reset_token))
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| AppError::Internal("Database error"))?;
+
+    let user_id: Uuid = match row {
+        Some(row) => row.get("user_id"),
+        None => return Err(AppError::InvalidToken("Invalid or expired reset token")),
+    };
+
+    let email: String =
+        sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2 RETURNING email")
+            .bind(&password_hash)
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await
+            .map(|row| row.get("email"))
+            .map_err(|_| AppError::Internal("Failed to update password"))?;
+
+    sqlx::query(
+        "UPDATE refresh_tokens SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to revoke sessions"))?;
+
+    sqlx::query("DELETE FROM login_attempts WHERE email = $1")
+        .bind(&email)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| AppError::Internal("Database error"))?;
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::Internal("Failed to commit transaction"))?;
+
+    Ok(())
+}
+
+pub fn issue_access_token(state: &AppState, user: &AuthenticatedUser) -> Result<String, AppError> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    let claims = AccessClaims {
+        sub: user.user_id.to_string(),
+        business_id: user.business_id.to_string(),
+        role: user.role,
+        iat: now,
+        exp: now + state.config.access_token_ttl.as_secs(),
+    };
+
+    encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(state.config.jwt_secret.as_bytes()),
+    )
+    .map_err(|_| AppError::Internal("Failed to sign access token"))
+}
+
+/// Returns the identity carried by a valid, unexpired access token.
+pub fn verify_access_token(state: &AppState, token: &str) -> Option<AuthContext> {
+    let data = decode::<AccessClaims>(
+        token,
+        &DecodingKey::from_secret(state.config.jwt_secret.as_bytes()),
+        &Validation::new(Algorithm::HS256),
+    )
+    .ok()?;
+
+    Some(AuthContext {
+        business_id: Uuid::parse_str(&data.claims.business_id).ok()?,
+        user_id: Some(Uuid::parse_str(&data.claims.sub).ok()?),
+        role: data.claims.role,
+        credential: CredentialKind::AccessToken,
+    })
+}
+
+async fn insert_refresh_token(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    state: &AppState,
+    user: &AuthenticatedUser,
+    family_id: Uuid,
+) -> Result<String, AppError> {
+    let refresh_token = generate_secret(REFRESH_TOKEN_PREFIX);
+
+    sqlx::query(
+        "INSERT INTO refresh_tokens (business_id, user_id, family_id, token_hash, expires_at) 
+         VALUES ($1, $2, $3, $4, NOW() + make_interval(secs => $5))",
+    )
+    .bind(user.business_id)
+    .bind(user.user_id)
+    .bind(family_id)
+    .bind(hash_token(&refresh_token))
+    .bind(state.config.refresh_token_ttl.as_secs() as f64)
+    .execute(&mut **tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to create refresh token"))?;
+
+    Ok(refresh_token)
+}
+
+fn session_tokens(
+    state: &AppState,
+    user: &AuthenticatedUser,
+    refresh_token: String,
+) -> Result<SessionTokens, AppError> {
+    Ok(SessionTokens {
+        access_token: issue_access_token(state, user)?,
+        refresh_token,
+        token_type: "Bearer".to_string(),
+        expires_in: state.config.access_token_ttl.as_secs(),
+    })
+}
+
+/// Starts a new session: a fresh refresh token family plus an access token.
+#[instrument(skip_all)]
+pub async fn create_session(
+    state: &AppState,
+    user: &AuthenticatedUser,
+) -> Result<SessionTokens, AppError> {
+    let mut tx = state
+        .pool
+        .begin()
+        .await
+        .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+    let refresh_token = insert_refresh_token(&mut tx, state, user, Uuid::new_v4()).await?;
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::Internal("Failed to commit transaction"))?;
+
+    session_tokens(state, user, refresh_token)
+}
+
+/// Exchanges a refresh token for a new token pair. Each refresh token is
+/// single-use; presenting one that was already rotated revokes its whole family.
+#[instrument(skip_all)]
+pub async fn rotate_refresh_token(
+    state: &AppState,
+    refresh_token: &str,
+) -> Result<SessionTokens, AppError> {
+    let mut tx = state
+        .pool
+        .begin()
+        .await
+        .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+    let row = sqlx::query(
+        "SELECT rt.id, rt.family_id, rt.revoked_at IS NOT NULL AS revoked, rt.expires_at < NOW() AS expired, 
+                u.id AS user_id, u.business_id, u.role 
+         FROM refresh_tokens rt 
+         JOIN users u ON u.id = rt.user_id 
+         WHERE rt.token_hash = $1 
+         FOR UPDATE OF rt",
+    )
+    .bind(hash_token(refresh_token))
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| AppError::Internal("Database error"))?;
+
+    let row = match row {
+        Some(row) => row,
+        None => return Err(AppError::InvalidToken("Invalid refresh token")),
+    };
+
+    let token_id: Uuid = row.get("id");
+    let family_id: Uuid = row.get("family_id");
+    // Re-read the role so changes take effect on the next refresh.
+    let user = AuthenticatedUser {
+        user_id: row.get("user_id"),
+        business_id: row.get("business_id"),
+        role: row.get("role"),
+    };
+
+    if row.get::<bool, _>("revoked") {
+        revoke_family(&mut tx, family_id).await?;
+        tx.commit()
+            .await
+            .map_err(|_| AppError::Internal("Failed to commit transaction"))?;
+        return Err(AppError::InvalidToken("Refresh token reuse detected"));
+    }
+
+    if row.get::<bool, _>("expired") {
+        return Err(AppError::InvalidToken("Refresh token expired"));
+    }
+
+    sqlx::query("UPDATE refresh_tokens SET revoked_at = NOW() WHERE id = $1")
+        .bind(token_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| AppError::Internal("Failed to rotate refresh token"))?;
+
+    let new_refresh_token = insert_refresh_token(&mut tx, state, &user, family_id).await?;
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::Internal("Failed to commit transaction"))?;
+
+    session_tokens(state, &user, new_refresh_token)
+}
+
+/// Ends the session the refresh token belongs to.
+#[instrument(skip_all)]
+pub async fn revoke_refresh_token(state: &AppState, refresh_token: &str) -> Result<(), AppError> {
+    let mut tx = state
+        .pool
+        .begin()
+        .await
+        .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+    let family_id = sqlx::query("SELECT family_id FROM refresh_tokens WHERE token_hash = $1")
+        .bind(hash_token(refresh_token))
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|_| AppError::Internal("Database error"))?
+        .map(|row| row.get::<Uuid, _>("family_id"));
+
+    match family_id {
+        Some(family_id) => revoke_family(&mut tx, family_id).await?,
+        None => return Err(AppError::InvalidToken("Invalid refresh token")),
+    }
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::Internal("Failed to commit transaction"))?;
+
+    Ok(())
+}
+
+async fn revoke_family(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    family_id: Uuid,
+) -> Result<(), AppError> {
+    sqlx::query(
+        "UPDATE refresh_tokens SET revoked_at = NOW() WHERE family_id = $1 AND revoked_at IS NULL",
+    )
+    .bind(family_id)
+    .execute(&mut **tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to revoke refresh tokens"))?;
+
+    Ok(())
+}
@@ -0,0 +1,551 @@
+use crate::config::AmountLimits;
+use crate::error::{AppError, FieldError};
+use crate::models::{
+    BatchItemResult, BatchMode, BatchResponse, BatchTransferItem, ItemError, TransferResponse,
+};
+use crate::services::accounts::{create_webhook_event, execute_transfer, lock_accounts};
+use crate::services::retry::with_retry;
+use crate::services::timestamps::to_rfc3339;
+use crate::state::AppState;
+use chrono::NaiveDateTime;
+use sqlx::{types::Uuid, PgExecutor, PgPool, Postgres, QueryBuilder, Row};
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::instrument;
+
+pub const MAX_BATCH_SIZE: usize = 1000;
+
+/// Batches up to this size run during the request; larger ones are queued for
+/// the batch worker.
+pub const SYNC_BATCH_SIZE: usize = 100;
+
+/// How often the worker looks for queued batches.
+const WORKER_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A batch left `processing` this long, by a worker or request that died, is
+/// picked up again. Items that already ran are not repeated.
+const CLAIM_TIMEOUT_SECS: f64 = 300.0;
+
+/// Returned when another worker has already recorded an item or finished the
+/// batch, after this worker's claim timed out. The worker stops and leaves the
+/// batch to the other one.
+const CLAIM_LOST: &str = "Batch was taken over by another worker";
+
+/// One transfer of a batch, parsed.
+pub struct BatchItem {
+    pub from_account_id: Uuid,
+    pub to_account_id: Uuid,
+    pub amount: i64,
+}
+
+/// Parses every item, reporting all invalid fields at once.
+pub fn validate_batch_items(transfers: &[BatchTransferItem]) -> Result<Vec<BatchItem>, AppError> {
+    if transfers.is_empty() {
+        return Err(AppError::InvalidRequest("transfers must not be empty"));
+    }
+    if transfers.len() > MAX_BATCH_SIZE {
+        return Err(AppError::InvalidRequest(
+            "A batch holds at most 1000 transfers",
+        ));
+    }
+
+    let mut errors = Vec::new();
+    let mut items = Vec::with_capacity(transfers.len());
+    for (index, transfer) in transfers.iter().enumerate() {
+        let mut parse = |field: &str, value: &str| {
+            Uuid::parse_str(value)
+                .map_err(|_| {
+                    errors.push(FieldError {
+                        field: format!("transfers[{index}].{field}"),
+                        message: "must be a UUID".to_string(),
+                    })
+                })
+                .ok()
+        };
+        let from_account_id = parse("from_account_id", &transfer.from_account_id);
+        let to_account_id = parse("to_account_id", &transfer.to_account_id);
+        if transfer.amount <= 0 {
+            errors.push(FieldError {
+                field: format!("transfers[{index}].amount"),
+                message: "must be positive".to_string(),
+            });
+        }
+        if from_account_id.is_some() && from_account_id == to_account_id {
+            errors.push(FieldError {
+                field: format!("transfers[{index}].to_account_id"),
+                message: "must differ from from_account_id".to_string(),
+            });
+        }
+        if let (Some(from_account_id), Some(to_account_id)) = (from_account_id, to_account_id) {
+            items.push(BatchItem {
+                from_account_id,
+                to_account_id,
+                amount: transfer.amount,
+            });
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(items)
+    } else {
+        Err(AppError::Validation(errors))
+    }
+}
+
+/// Stores a batch and its items. A batch run during the request is created
+/// already claimed, so the worker leaves it alone.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn create_batch(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    business_id: Uuid,
+    idempotency_key: &str,
+    mode: BatchMode,
+    items: &[BatchItem],
+    queued: bool,
+    request_id: &str,
+) -> Result<Uuid, AppError> {
+    let batch_id: Uuid = sqlx::query(
+        "INSERT INTO transfer_batches (business_id, idempotency_key, mode, status, claimed_at, request_id)
+         VALUES ($1, $2, $3, $4, CASE WHEN $5 THEN NULL ELSE NOW() END, $6) RETURNING id",
+    )
+    .bind(business_id)
+    .bind(idempotency_key)
+    .bind(mode.as_str())
+    .bind(if queued { "queued" } else { "processing" })
+    .bind(queued)
+    .bind(request_id)
+    .fetch_one(&mut **tx)
+    .await
+    .map(|row| row.get("id"))
+    .map_err(|_| AppError::Internal("Failed to create batch"))?;
+
+    let mut builder = QueryBuilder::<Postgres>::new(
+        "INSERT INTO transfer_batch_items (batch_id, position, from_account_id, to_account_id, amount) ",
+    );
+    builder.push_values(items.iter().enumerate(), |mut row, (position, item)| {
+        row.push_bind(batch_id)
+            .push_bind(position as i32)
+            .push_bind(item.from_account_id)
+            .push_bind(item.to_account_id)
+            .push_bind(item.amount);
+    });
+    builder
+        .build()
+        .execute(&mut **tx)
+        .await
+        .map_err(|_| AppError::Internal("Failed to create batch items"))?;
+
+    Ok(batch_id)
+}
+
+/// A batch with its counts but without items, as stored for idempotent
+/// replays and sent in `batch.completed` webhooks.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn fetch_batch_summary<'e>(
+    executor: impl PgExecutor<'e>,
+    business_id: Uuid,
+    batch_id: Uuid,
+) -> Result<BatchResponse, AppError> {
+    let row = sqlx::query(
+        "SELECT b.id, b.mode, b.status, b.created_at, b.completed_at,
+                COUNT(i.position) AS total_count,
+                COUNT(i.position) FILTER (WHERE i.status = 'succeeded') AS succeeded_count,
+                COUNT(i.position) FILTER (WHERE i.status = 'failed') AS failed_count
+         FROM transfer_batches b
+         LEFT JOIN transfer_batch_items i ON i.batch_id = b.id
+         WHERE b.id = $1 AND b.business_id = $2
+         GROUP BY b.id",
+    )
+    .bind(batch_id)
+    .bind(business_id)
+    .fetch_optional(executor)
+    .await
+    .map_err(|_| AppError::Internal("Failed to fetch batch"))?
+    .ok_or(AppError::NotFound("Batch not found"))?;
+
+    let mode: String = row.get("mode");
+    Ok(BatchResponse {
+        id: batch_id.to_string(),
+        mode: if mode == "best_effort" {
+            BatchMode::BestEffort
+        } else {
+            BatchMode::AllOrNothing
+        },
+        status: row.get("status"),
+        total_count: row.get("total_count"),
+        succeeded_count: row.get("succeeded_count"),
+        failed_count: row.get("failed_count"),
+        items: Vec::new(),
+        created_at: to_rfc3339(row.get("created_at")),
+        completed_at: row
+            .get::<Option<NaiveDateTime>, _>("completed_at")
+            .map(to_rfc3339),
+        cached: None,
+    })
+}
+
+/// A batch with every item's result, in request order.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn fetch_batch(
+    pool: &PgPool,
+    business_id: Uuid,
+    batch_id: Uuid,
+) -> Result<BatchResponse, AppError> {
+    let mut batch = fetch_batch_summary(pool, business_id, batch_id).await?;
+
+    batch.items = sqlx::query(
+        "SELECT position, from_account_id, to_account_id, amount, status, transaction_id, error_code, error_message
+         FROM transfer_batch_items WHERE batch_id = $1 ORDER BY position",
+    )
+    .bind(batch_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|_| AppError::Internal("Failed to fetch batch items"))?
+    .iter()
+    .map(|row| BatchItemResult {
+        index: row.get("position"),
+        from_account_id: row.get::<Uuid, _>("from_account_id").to_string(),
+        to_account_id: row.get::<Uuid, _>("to_account_id").to_string(),
+        amount: row.get("amount"),
+        status: row.get("status"),
+        transaction_id: row
+            .get::<Option<Uuid>, _>("transaction_id")
+            .map(|id| id.to_string()),
+        error: row
+            .get::<Option<String>, _>("error_code")
+            .map(|code| ItemError {
+                code,
+                message: row.get("error_message"),
+            }),
+    })
+    .collect();
+
+    Ok(batch)
+}
+
+/// A stored item not yet run.
+struct QueuedItem {
+    position: i32,
+    item: BatchItem,
+}
+
+/// Runs one item as an ordinary transfer, with an idempotency key derived
+/// from the batch so it shows up on the transaction.
+async fn transfer_item(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    limits: &HashMap<String, AmountLimits>,
+    business_id: Uuid,
+    batch_id: Uuid,
+    queued: &QueuedItem,
+    request_id: &str,
+) -> Result<TransferResponse, AppError> {
+    let item = &queued.item;
+    let (transaction_id, response) = execute_transfer(
+        tx,
+        limits,
+        business_id,
+        item.from_account_id,
+        item.to_account_id,
+        item.amount,
+        &format!("{batch_id}/{}", queued.position),
+        request_id,
+    )
+    .await?;
+
+    let updated = sqlx::query(
+        "UPDATE transfer_batch_items SET status = 'succeeded', transaction_id = $1
+         WHERE batch_id = $2 AND position = $3 AND status = 'queued'",
+    )
+    .bind(transaction_id)
+    .bind(batch_id)
+    .bind(queued.position)
+    .execute(&mut **tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to update batch item"))?;
+    if updated.rows_affected() == 0 {
+        return Err(AppError::Conflict(CLAIM_LOST));
+    }
+
+    Ok(response)
+}
+
+async fn mark_item_failed<'e>(
+    executor: impl PgExecutor<'e>,
+    batch_id: Uuid,
+    position: i32,
+    error: &AppError,
+) -> Result<(), AppError> {
+    let updated = sqlx::query(
+        "UPDATE transfer_batch_items SET status = 'failed', error_code = $1, error_message = $2
+         WHERE batch_id = $3 AND position = $4 AND status = 'queued'",
+    )
+    .bind(error.code())
+    .bind(error.message())
+    .bind(batch_id)
+    .bind(position)
+    .execute(executor)
+    .await
+    .map_err(|_| AppError::Internal("Failed to update batch item"))?;
+    if updated.rows_affected() == 0 {
+        return Err(AppError::Conflict(CLAIM_LOST));
+    }
+    Ok(())
+}
+
+/// Runs every item in one database transaction. Accounts are locked up front
+/// in id order, so concurrent batches cannot deadlock on each other. The first
+/// failure rolls everything back and the other items are marked `skipped`.
+async fn run_all_or_nothing(
+    state: &AppState,
+    business_id: Uuid,
+    batch_id: Uuid,
+    items: &[QueuedItem],
+    request_id: &str,
+) -> Result<bool, AppError> {
+    let mut tx = state
+        .pool
+        .begin()
+        .await
+        .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+    let account_ids: Vec<Uuid> = items
+        .iter()
+        .flat_map(|queued| [queued.item.from_account_id, queued.item.to_account_id])
+        .collect();
+    lock_accounts(&mut tx, &account_ids).await?;
+
+    let mut transfers = Vec::with_capacity(items.len());
+    for queued in items {
+        match transfer_item(
+            &mut tx,
+            &state.config.amount_limits,
+            business_id,
+            batch_id,
+            queued,
+            request_id,
+        )
+        .await
+        {
+            Ok(response) => transfers.push(response),
+            Err(error @ (AppError::Conflict(CLAIM_LOST) | AppError::Contention(_))) => {
+                return Err(error)
+            }
+            Err(error) => {
+                drop(tx);
+                let mut tx = state
+                    .pool
+                    .begin()
+                    .await
+                    .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+                mark_item_failed(&mut *tx, batch_id, queued.position, &error).await?;
+                sqlx::query(
+                    "UPDATE transfer_batch_items SET status = 'skipped'
+                     WHERE batch_id = $1 AND status = 'queued'",
+                )
+                .bind(batch_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|_| AppError::Internal("Failed to update batch items"))?;
+                tx.commit()
+                    .await
+                    .map_err(|_| AppError::Internal("Failed to commit transaction"))?;
+                return Ok(false);
+            }
+        }
+    }
+
+    tx.commit()
+        .await
+        .map_err(AppError::database("Failed to commit transaction"))?;
+
+    for transfer in &transfers {
+        state
+            .metrics
+            .record_money_movement("transfer", &transfer.currency, transfer.amount);
+    }
+    Ok(true)
+}
+
+/// Runs each item in its own database transaction, recording failures and
+/// carrying on. An item that keeps hitting contention leaves the batch
+/// `processing`, so it is picked up again once its claim times out.
+async fn run_best_effort(
+    state: &AppState,
+    business_id: Uuid,
+    batch_id: Uuid,
+    items: &[QueuedItem],
+    request_id: &str,
+) -> Result<(), AppError> {
+    for queued in items {
+        let outcome = with_retry(|| async {
+            let mut tx = state
+                .pool
+                .begin()
+                .await
+                .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+            match transfer_item(
+                &mut tx,
+                &state.config.amount_limits,
+                business_id,
+                batch_id,
+                queued,
+                request_id,
+            )
+            .await
+            {
+                Ok(transfer) => {
+                    tx.commit()
+                        .await
+                        .map_err(AppError::database("Failed to commit transaction"))?;
+                    Ok(Ok(transfer))
+                }
+                Err(error @ (AppError::Conflict(CLAIM_LOST) | AppError::Contention(_))) => {
+                    Err(error)
+                }
+                Err(error) => Ok(Err(error)),
+            }
+        })
+        .await?;
+
+        match outcome {
+            Ok(transfer) => {
+                state.metrics.record_money_movement(
+                    "transfer",
+                    &transfer.currency,
+                    transfer.amount,
+                );
+            }
+            Err(error) => {
+                mark_item_failed(&state.pool, batch_id, queued.position, &error).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs the queued items of a claimed batch, then marks it `completed`, or
+/// `failed` if an all-or-nothing batch was rolled back, and sends
+/// `batch.completed`. Item and batch outcomes are only written over `queued`
+/// and `processing`, so a worker whose claim timed out cannot overwrite what
+/// the worker that took over recorded.
+#[instrument(skip_all, fields(%batch_id))]
+pub async fn process_batch(state: &AppState, batch_id: Uuid) -> Result<(), AppError> {
+    let batch =
+        sqlx::query("SELECT business_id, mode, request_id FROM transfer_batches WHERE id = $1")
+            .bind(batch_id)
+            .fetch_one(&state.pool)
+            .await
+            .map_err(|_| AppError::Internal("Failed to fetch batch"))?;
+    let business_id: Uuid = batch.get("business_id");
+    let mode: String = batch.get("mode");
+    let request_id: Option<String> = batch.get("request_id");
+    let request_id = request_id.unwrap_or_default();
+
+    let items: Vec<QueuedItem> = sqlx::query(
+        "SELECT position, from_account_id, to_account_id, amount FROM transfer_batch_items
+         WHERE batch_id = $1 AND status = 'queued' ORDER BY position",
+    )
+    .bind(batch_id)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(|_| AppError::Internal("Failed to fetch batch items"))?
+    .iter()
+    .map(|row| QueuedItem {
+        position: row.get("position"),
+        item: BatchItem {
+            from_account_id: row.get("from_account_id"),
+            to_account_id: row.get("to_account_id"),
+            amount: row.get("amount"),
+        },
+    })
+    .collect();
+
+    let succeeded = if mode == BatchMode::BestEffort.as_str() {
+        run_best_effort(state, business_id, batch_id, &items, &request_id).await?;
+        true
+    } else {
+        with_retry(|| run_all_or_nothing(state, business_id, batch_id, &items, &request_id)).await?
+    };
+
+    let mut tx = state
+        .pool
+        .begin()
+        .await
+        .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+    let updated = sqlx::query(
+        "UPDATE transfer_batches SET status = $1, completed_at = NOW()
+         WHERE id = $2 AND status = 'processing'",
+    )
+    .bind(if succeeded { "completed" } else { "failed" })
+    .bind(batch_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to update batch"))?;
+    if updated.rows_affected() == 0 {
+        return Err(AppError::Conflict(CLAIM_LOST));
+    }
+    let summary = fetch_batch_summary(&mut *tx, business_id, batch_id).await?;
+    create_webhook_event(
+        &mut tx,
+        business_id,
+        "batch.completed",
+        &summary,
+        &request_id,
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|_| AppError::Internal("Failed to commit transaction"))?;
+
+    Ok(())
+}
+
+/// Claims and runs queued batches, plus any abandoned mid-run, until none are
+/// left. Returns how many were processed.
+#[instrument(skip_all)]
+pub async fn process_queued_batches(state: &AppState) -> Result<usize, AppError> {
+    let mut processed = 0;
+    loop {
+        // Concurrent workers skip each other's batches rather than wait
+        let claimed: Option<Uuid> = sqlx::query_scalar(
+            "UPDATE transfer_batches SET status = 'processing', claimed_at = NOW()
+             WHERE id = (
+                 SELECT id FROM transfer_batches
+                 WHERE status = 'queued'
+                    OR (status = 'processing' AND claimed_at < NOW() - make_interval(secs => $1))
+                 ORDER BY created_at
+                 LIMIT 1
+                 FOR UPDATE SKIP LOCKED
+             )
+             RETURNING id",
+        )
+        .bind(CLAIM_TIMEOUT_SECS)
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(|_| AppError::Internal("Failed to claim batch"))?;
+
+        let Some(batch_id) = claimed else {
+            return Ok(processed);
+        };
+        match process_batch(state, batch_id).await {
+            Err(AppError::Conflict(CLAIM_LOST)) => {
+                tracing::warn!(%batch_id, "{CLAIM_LOST}");
+            }
+            result => result?,
+        }
+        processed += 1;
+    }
+}
+
+pub async fn run_batch_worker(state: AppState) {
+    let mut ticker = tokio::time::interval(WORKER_INTERVAL);
+    loop {
+        ticker.tick().await;
+        match process_queued_batches(&state).await {
+            Ok(0) => {}
+            Ok(count) => tracing::info!(count, "Processed transfer batches"),
+            Err(e) => tracing::error!(error = ?e, "Failed to process transfer batches"),
+        }
+    }
+}
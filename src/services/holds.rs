@@ -0,0 +1,215 @@
+use crate::error::AppError;
+use crate::models::HoldResponse;
+use crate::services::accounts::{create_webhook_event, fetch_account};
+use crate::services::timestamps::to_rfc3339;
+use crate::state::AppState;
+use sqlx::postgres::PgRow;
+use sqlx::{types::Uuid, PgExecutor, Postgres, Row};
+use std::time::Duration;
+use tracing::instrument;
+
+pub const DEFAULT_HOLD_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+pub const MAX_HOLD_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// How often lapsed holds are marked `expired`. Their funds are released the
+/// moment they expire regardless; this only updates status and sends webhooks.
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// SQL expression for the amount held on account `a` by active, unexpired holds
+/// and by pending transfers out of it.
+pub const HELD_AMOUNT: &str = "(COALESCE((SELECT SUM(h.amount) FROM holds h WHERE h.account_id = a.id AND h.status = 'active' AND h.expires_at > NOW()), 0)
+     + COALESCE((SELECT SUM(p.amount) FROM transactions p WHERE p.from_account_id = a.id AND p.status = 'pending'), 0))::BIGINT";
+
+/// Reports lapsed holds as `expired` even before the expiry job has run.
+const SELECT_HOLD: &str = "SELECT h.id, h.account_id, h.amount, a.currency,
+            CASE WHEN h.status = 'active' AND h.expires_at <= NOW() THEN 'expired' ELSE h.status END AS status,
+            h.captured_amount, h.transaction_id, h.expires_at, h.created_at
+     FROM holds h JOIN accounts a ON a.id = h.account_id
+     WHERE h.id = $1 AND h.business_id = $2";
+
+fn hold_from_row(row: &PgRow) -> HoldResponse {
+    HoldResponse {
+        id: row.get::<Uuid, _>("id").to_string(),
+        account_id: row.get::<Uuid, _>("account_id").to_string(),
+        amount: row.get("amount"),
+        currency: row.get("currency"),
+        status: row.get("status"),
+        captured_amount: row.get("captured_amount"),
+        transaction_id: row
+            .get::<Option<Uuid>, _>("transaction_id")
+            .map(|id| id.to_string()),
+        expires_at: to_rfc3339(row.get("expires_at")),
+        created_at: to_rfc3339(row.get("created_at")),
+        cached: None,
+    }
+}
+
+/// Validates a requested hold lifetime, in seconds.
+pub fn hold_ttl(expires_in_seconds: Option<i64>) -> Result<i64, AppError> {
+    match expires_in_seconds {
+        None => Ok(DEFAULT_HOLD_TTL.as_secs() as i64),
+        Some(secs) if secs > 0 && secs <= MAX_HOLD_TTL.as_secs() as i64 => Ok(secs),
+        Some(_) => Err(AppError::InvalidRequest(
+            "expires_in_seconds must be between 1 and 2592000",
+        )),
+    }
+}
+
+#[instrument(skip_all, fields(%business_id))]
+pub async fn fetch_hold<'e>(
+    executor: impl PgExecutor<'e>,
+    business_id: Uuid,
+    hold_id: Uuid,
+) -> Result<HoldResponse, AppError> {
+    let row = sqlx::query(SELECT_HOLD)
+        .bind(hold_id)
+        .bind(business_id)
+        .fetch_optional(executor)
+        .await
+        .map_err(|_| AppError::Internal("Failed to fetch hold"))?
+        .ok_or(AppError::NotFound("Hold not found"))?;
+    Ok(hold_from_row(&row))
+}
+
+/// Reserves `amount` on one of the caller's accounts. The account row lock
+/// serializes this with debits, transfers and other holds.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn create_hold(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    business_id: Uuid,
+    account_id: Uuid,
+    amount: i64,
+    ttl_secs: i64,
+) -> Result<HoldResponse, AppError> {
+    let (_, _, available) = fetch_account(tx, account_id, business_id).await?;
+    if available < amount {
+        return Err(AppError::InsufficientFunds {
+            available,
+            required: amount,
+        });
+    }
+
+    let hold_id: Uuid = sqlx::query(
+        "INSERT INTO holds (business_id, account_id, amount, expires_at)
+         VALUES ($1, $2, $3, NOW() + make_interval(secs => $4)) RETURNING id",
+    )
+    .bind(business_id)
+    .bind(account_id)
+    .bind(amount)
+    .bind(ttl_secs as f64)
+    .fetch_one(&mut **tx)
+    .await
+    .map(|row| row.get("id"))
+    .map_err(|_| AppError::Internal("Failed to create hold"))?;
+
+    fetch_hold(&mut **tx, business_id, hold_id).await
+}
+
+/// Locks an active, unexpired hold and returns its account and amount.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn lock_active_hold(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    business_id: Uuid,
+    hold_id: Uuid,
+) -> Result<(Uuid, i64), AppError> {
+    let row = sqlx::query(
+        "SELECT account_id, amount, status, expires_at <= NOW() AS lapsed
+         FROM holds WHERE id = $1 AND business_id = $2 FOR UPDATE",
+    )
+    .bind(hold_id)
+    .bind(business_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to fetch hold"))?
+    .ok_or(AppError::NotFound("Hold not found"))?;
+
+    let status: String = row.get("status");
+    match status.as_str() {
+        "active" if row.get::<bool, _>("lapsed") => Err(AppError::Conflict("Hold has expired")),
+        "active" => Ok((row.get("account_id"), row.get("amount"))),
+        "captured" => Err(AppError::Conflict("Hold has already been captured")),
+        "voided" => Err(AppError::Conflict("Hold has been voided")),
+        _ => Err(AppError::Conflict("Hold has expired")),
+    }
+}
+
+/// Moves a locked hold out of `active`, releasing its reservation for the rest
+/// of the transaction.
+#[instrument(skip_all)]
+pub async fn close_hold(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    hold_id: Uuid,
+    status: &str,
+    captured_amount: Option<i64>,
+) -> Result<(), AppError> {
+    sqlx::query(
+        "UPDATE holds SET status = $1, captured_amount = $2, updated_at = NOW() WHERE id = $3",
+    )
+    .bind(status)
+    .bind(captured_amount)
+    .bind(hold_id)
+    .execute(&mut **tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to update hold"))?;
+    Ok(())
+}
+
+#[instrument(skip_all)]
+pub async fn link_hold_transaction(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    hold_id: Uuid,
+    transaction_id: Uuid,
+) -> Result<(), AppError> {
+    sqlx::query("UPDATE holds SET transaction_id = $1 WHERE id = $2")
+        .bind(transaction_id)
+        .bind(hold_id)
+        .execute(&mut **tx)
+        .await
+        .map_err(|_| AppError::Internal("Failed to update hold"))?;
+    Ok(())
+}
+
+/// Marks lapsed holds `expired` and sends `hold.expired` webhooks.
+#[instrument(skip_all)]
+pub async fn expire_holds(state: &AppState) -> Result<usize, AppError> {
+    let mut tx = state
+        .pool
+        .begin()
+        .await
+        .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+    let expired = sqlx::query(
+        "UPDATE holds SET status = 'expired', updated_at = NOW()
+         WHERE status = 'active' AND expires_at <= NOW()
+         RETURNING id, business_id",
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to expire holds"))?;
+
+    // Webhooks from one run share a request ID, for correlation in logs
+    let run_id = Uuid::new_v4().to_string();
+    for row in &expired {
+        let business_id: Uuid = row.get("business_id");
+        let hold = fetch_hold(&mut *tx, business_id, row.get("id")).await?;
+        create_webhook_event(&mut tx, business_id, "hold.expired", &hold, &run_id).await?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::Internal("Failed to commit transaction"))?;
+
+    Ok(expired.len())
+}
+
+pub async fn run_expiry(state: AppState) {
+    let mut ticker = tokio::time::interval(EXPIRY_INTERVAL);
+    loop {
+        ticker.tick().await;
+        match expire_holds(&state).await {
+            Ok(0) => {}
+            Ok(count) => tracing::info!(count, "Expired holds"),
+            Err(e) => tracing::error!(error = ?e, "Failed to expire holds"),
+        }
+    }
+}
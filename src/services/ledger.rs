@@ -0,0 +1,371 @@
+use crate::error::AppError;
+use crate::models::{BalanceDiscrepancy, CurrencyVerification, LedgerVerificationReport};
+use crate::services::timestamps::to_rfc3339;
+use crate::state::AppState;
+use chrono::Utc;
+use sqlx::postgres::PgRow;
+use sqlx::{types::Uuid, PgPool, Postgres, QueryBuilder, Row};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tracing::instrument;
+
+/// Platform-side accounts holding the other leg of money entering or leaving
+/// customer accounts. They have no row in `accounts`; their balance per
+/// currency is the sum of their postings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SystemAccount {
+    /// Source of credits: money paid in from outside the platform.
+    ExternalFunding,
+    /// Destination of debits: money paid out of the platform.
+    ExternalPayouts,
+    /// Source of the balance an account is opened with.
+    OpeningBalances,
+}
+
+impl SystemAccount {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::ExternalFunding => "external_funding",
+            Self::ExternalPayouts => "external_payouts",
+            Self::OpeningBalances => "opening_balances",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LedgerAccount {
+    Customer(Uuid),
+    System(SystemAccount),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Debit,
+    Credit,
+}
+
+impl Direction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Debit => "debit",
+            Self::Credit => "credit",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Posting {
+    pub account: LedgerAccount,
+    pub direction: Direction,
+    pub amount: i64,
+}
+
+impl Posting {
+    pub fn debit(account: LedgerAccount, amount: i64) -> Self {
+        Self {
+            account,
+            direction: Direction::Debit,
+            amount,
+        }
+    }
+
+    pub fn credit(account: LedgerAccount, amount: i64) -> Self {
+        Self {
+            account,
+            direction: Direction::Credit,
+            amount,
+        }
+    }
+}
+
+/// One balanced, single-currency movement of money.
+pub struct JournalEntry<'a> {
+    /// `transfer`, `credit`, `debit`, `reversal`, `multi_leg` or
+    /// `opening_balance`.
+    pub kind: &'a str,
+    pub currency: &'a str,
+    pub transaction_id: Option<Uuid>,
+    pub postings: Vec<Posting>,
+}
+
+impl<'a> JournalEntry<'a> {
+    /// The two postings moving `amount` out of `from` and into `to`.
+    pub fn movement(
+        kind: &'a str,
+        currency: &'a str,
+        transaction_id: Option<Uuid>,
+        from: LedgerAccount,
+        to: LedgerAccount,
+        amount: i64,
+    ) -> Self {
+        Self {
+            kind,
+            currency,
+            transaction_id,
+            postings: vec![Posting::debit(from, amount), Posting::credit(to, amount)],
+        }
+    }
+
+    fn is_balanced(&self) -> bool {
+        let mut net: i128 = 0;
+        for posting in &self.postings {
+            if posting.amount <= 0 {
+                return false;
+            }
+            match posting.direction {
+                Direction::Debit => net += posting.amount as i128,
+                Direction::Credit => net -= posting.amount as i128,
+            }
+        }
+        !self.postings.is_empty() && net == 0
+    }
+}
+
+/// Writes `entry` to the ledger and applies it to the cached balances of the
+/// customer accounts it touches. Callers hold the account row locks and have
+/// already checked currencies and available funds. The database re-checks the
+/// balance at commit.
+#[instrument(skip_all, fields(kind = entry.kind))]
+pub async fn post(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    entry: &JournalEntry<'_>,
+) -> Result<Uuid, AppError> {
+    if !entry.is_balanced() {
+        return Err(AppError::Internal("Unbalanced journal entry"));
+    }
+
+    let journal_entry_id: Uuid = sqlx::query(
+        "INSERT INTO journal_entries (transaction_id, kind, currency) VALUES ($1, $2, $3) RETURNING id",
+    )
+    .bind(entry.transaction_id)
+    .bind(entry.kind)
+    .bind(entry.currency)
+    .fetch_one(&mut **tx)
+    .await
+    .map(|row| row.get("id"))
+    .map_err(|_| AppError::Internal("Failed to create journal entry"))?;
+
+    let mut builder = QueryBuilder::<Postgres>::new(
+        "INSERT INTO ledger_entries (journal_entry_id, account_id, system_account, direction, amount) ",
+    );
+    builder.push_values(&entry.postings, |mut row, posting| {
+        let (account_id, system_account) = match posting.account {
+            LedgerAccount::Customer(id) => (Some(id), None),
+            LedgerAccount::System(system) => (None, Some(system.as_str())),
+        };
+        row.push_bind(journal_entry_id)
+            .push_bind(account_id)
+            .push_bind(system_account)
+            .push_bind(posting.direction.as_str())
+            .push_bind(posting.amount);
+    });
+    builder
+        .build()
+        .execute(&mut **tx)
+        .await
+        .map_err(|_| AppError::Internal("Failed to write ledger entries"))?;
+
+    // Net change per customer account, in a stable order
+    let mut deltas: BTreeMap<Uuid, i64> = BTreeMap::new();
+    for posting in &entry.postings {
+        if let LedgerAccount::Customer(id) = posting.account {
+            let delta = match posting.direction {
+                Direction::Credit => posting.amount,
+                Direction::Debit => -posting.amount,
+            };
+            let net = deltas.entry(id).or_default();
+            *net = net
+                .checked_add(delta)
+                .ok_or(AppError::InvalidRequest("Amount is too large"))?;
+        }
+    }
+
+    // Checked in SQL, so a balance near the BIGINT limit is rejected with a
+    // clear error rather than failing the query
+    for (account_id, delta) in deltas {
+        let updated = sqlx::query(
+            "UPDATE accounts SET balance = balance + $1
+             WHERE id = $2 AND ($1 <= 0 OR balance <= 9223372036854775807 - $1)",
+        )
+        .bind(delta)
+        .bind(account_id)
+        .execute(&mut **tx)
+        .await
+        .map_err(AppError::database("Failed to update balance"))?;
+        if updated.rows_affected() == 0 {
+            return Err(AppError::InvalidRequest(
+                "Amount would overflow the account balance",
+            ));
+        }
+    }
+
+    Ok(journal_entry_id)
+}
+
+/// Each account's cached balance next to the sum of its postings. Sums stay
+/// `NUMERIC` and are read as text, so a total past the `BIGINT` range is still
+/// reported rather than failing the check.
+const ACCOUNT_LEDGER_BALANCES: &str = "WITH posted AS (
+         SELECT account_id, SUM(CASE direction WHEN 'credit' THEN amount ELSE -amount END) AS ledger_balance
+         FROM ledger_entries
+         WHERE account_id IS NOT NULL
+         GROUP BY account_id
+     ), checked AS (
+         SELECT a.id, a.business_id, a.currency, a.balance, COALESCE(p.ledger_balance, 0) AS ledger_balance
+         FROM accounts a
+         LEFT JOIN posted p ON p.account_id = a.id
+     ) ";
+
+/// Recomputes every account's balance from its ledger postings and reports
+/// where it disagrees with `accounts.balance`, plus any journal entry or
+/// transaction that breaks double-entry. Reads one snapshot, so money moving
+/// during the check cannot show up as a false discrepancy.
+#[instrument(skip_all)]
+pub async fn verify(pool: &PgPool) -> Result<LedgerVerificationReport, AppError> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+    let currencies: Vec<CurrencyVerification> = sqlx::query(&format!(
+        "{ACCOUNT_LEDGER_BALANCES}
+         SELECT currency, COUNT(*) AS accounts,
+                SUM(balance)::TEXT AS balance, SUM(ledger_balance)::TEXT AS ledger_balance,
+                COUNT(*) FILTER (WHERE balance <> ledger_balance) AS discrepancies
+         FROM checked
+         GROUP BY currency
+         ORDER BY currency"
+    ))
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to verify balances"))?
+    .iter()
+    .map(|row| {
+        let balance = numeric(row, "balance")?;
+        let ledger_balance = numeric(row, "ledger_balance")?;
+        Ok(CurrencyVerification {
+            currency: row.get("currency"),
+            accounts: row.get("accounts"),
+            balance,
+            ledger_balance,
+            difference: balance - ledger_balance,
+            discrepancies: row.get("discrepancies"),
+        })
+    })
+    .collect::<Result<_, AppError>>()?;
+
+    let discrepancies: Vec<BalanceDiscrepancy> = sqlx::query(&format!(
+        "{ACCOUNT_LEDGER_BALANCES}
+         SELECT id, business_id, currency, balance::TEXT AS balance, ledger_balance::TEXT AS ledger_balance
+         FROM checked
+         WHERE balance <> ledger_balance
+         ORDER BY currency, id"
+    ))
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to verify balances"))?
+    .iter()
+    .map(|row| {
+        let balance = numeric(row, "balance")?;
+        let ledger_balance = numeric(row, "ledger_balance")?;
+        Ok(BalanceDiscrepancy {
+            account_id: row.get::<Uuid, _>("id").to_string(),
+            business_id: row.get::<Uuid, _>("business_id").to_string(),
+            currency: row.get("currency"),
+            balance,
+            ledger_balance,
+            difference: balance - ledger_balance,
+        })
+    })
+    .collect::<Result<_, AppError>>()?;
+
+    let unbalanced_journal_entries: Vec<String> = sqlx::query_scalar::<_, Uuid>(
+        "SELECT journal_entry_id FROM ledger_entries
+         GROUP BY journal_entry_id
+         HAVING SUM(CASE direction WHEN 'debit' THEN amount ELSE -amount END) <> 0
+         ORDER BY journal_entry_id",
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to verify journal entries"))?
+    .into_iter()
+    .map(|id| id.to_string())
+    .collect();
+
+    let unposted_transactions: Vec<String> = sqlx::query_scalar::<_, Uuid>(
+        "SELECT t.id FROM transactions t
+         WHERE t.status IN ('success', 'settled')
+           AND NOT EXISTS (SELECT 1 FROM journal_entries j WHERE j.transaction_id = t.id)
+         ORDER BY t.created_at, t.id",
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to verify transactions"))?
+    .into_iter()
+    .map(|id| id.to_string())
+    .collect();
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::Internal("Failed to commit transaction"))?;
+
+    Ok(LedgerVerificationReport {
+        ok: discrepancies.is_empty()
+            && unbalanced_journal_entries.is_empty()
+            && unposted_transactions.is_empty(),
+        checked_at: to_rfc3339(Utc::now().naive_utc()),
+        accounts_checked: currencies.iter().map(|currency| currency.accounts).sum(),
+        currencies,
+        discrepancies,
+        unbalanced_journal_entries,
+        unposted_transactions,
+    })
+}
+
+/// Reads a whole-number `NUMERIC` column selected as text. Sums of `BIGINT`s
+/// over any realistic number of rows fit in an `i128`.
+fn numeric(row: &PgRow, column: &str) -> Result<i128, AppError> {
+    row.get::<String, _>(column)
+        .parse()
+        .map_err(|_| AppError::Internal("Failed to verify balances"))
+}
+
+/// Verifies the ledger every `interval`, publishing the outcome as metrics and
+/// logging an error for each run that finds problems, for alerting.
+pub async fn run_verification_schedule(state: AppState, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        match verify(&state.pool).await {
+            Ok(report) => {
+                state.metrics.record_ledger_verification(&report);
+                if report.ok {
+                    tracing::info!(
+                        accounts_checked = report.accounts_checked,
+                        "Ledger verification passed"
+                    );
+                } else {
+                    tracing::error!(
+                        accounts_checked = report.accounts_checked,
+                        discrepancies = report.discrepancies.len(),
+                        unbalanced_journal_entries = report.unbalanced_journal_entries.len(),
+                        unposted_transactions = report.unposted_transactions.len(),
+                        "Ledger verification found discrepancies"
+                    );
+                }
+            }
+            Err(e) => {
+                state
+                    .metrics
+                    .ledger_verifications
+                    .with_label_values(&["error"])
+                    .inc();
+                tracing::error!(error = ?e, "Ledger verification failed to run");
+            }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use crate::config::AmountLimits;
+use crate::error::{AppError, FieldError};
+use crate::state::AppState;
+use sqlx::types::Uuid;
+use std::collections::HashMap;
+use tracing::instrument;
+
+/// The field error for `amount` if it is outside `limits` for `currency`.
+pub fn amount_limit_error(
+    limits: &HashMap<String, AmountLimits>,
+    field: &str,
+    currency: &str,
+    amount: i64,
+) -> Option<FieldError> {
+    let limits = limits.get(&currency.to_ascii_uppercase())?;
+    let message = if amount < limits.min {
+        format!("must be at least {} for {currency}", limits.min)
+    } else if amount > limits.max {
+        format!("must be at most {} for {currency}", limits.max)
+    } else {
+        return None;
+    };
+    Some(FieldError {
+        field: field.to_string(),
+        message,
+    })
+}
+
+/// Checks `amount` against `limits` for `currency`, inside the database
+/// transaction that moves it.
+pub fn check_currency_limits(
+    limits: &HashMap<String, AmountLimits>,
+    field: &str,
+    currency: &str,
+    amount: i64,
+) -> Result<(), AppError> {
+    match amount_limit_error(limits, field, currency, amount) {
+        Some(error) => Err(AppError::Validation(vec![error])),
+        None => Ok(()),
+    }
+}
+
+/// Checks `amount` against the limits for the currency of `account_id`.
+pub async fn check_amount_limits(
+    state: &AppState,
+    field: &str,
+    account_id: Uuid,
+    amount: i64,
+) -> Result<(), AppError> {
+    check_all_amount_limits(state, &[(field.to_string(), account_id, amount)]).await
+}
+
+/// Checks `(field, account_id, amount)` entries against the limits for each
+/// account's currency, reporting every one outside them. Currencies never
+/// change, so this runs before the money-moving database transaction. Unknown
+/// accounts pass; the operation itself reports them.
+#[instrument(skip_all)]
+pub async fn check_all_amount_limits(
+    state: &AppState,
+    entries: &[(String, Uuid, i64)],
+) -> Result<(), AppError> {
+    if state.config.amount_limits.is_empty() {
+        return Ok(());
+    }
+    let account_ids: Vec<Uuid> = entries.iter().map(|(_, id, _)| *id).collect();
+    let currencies: HashMap<Uuid, String> =
+        sqlx::query_as("SELECT id, currency FROM accounts WHERE id = ANY($1)")
+            .bind(&account_ids)
+            .fetch_all(&state.pool)
+            .await
+            .map_err(|_| AppError::Internal("Failed to fetch accounts"))?
+            .into_iter()
+            .collect();
+
+    let errors: Vec<FieldError> = entries
+        .iter()
+        .filter_map(|(field, account_id, amount)| {
+            let currency = currencies.get(account_id)?;
+            amount_limit_error(&state.config.amount_limits, field, currency, *amount)
+        })
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::Validation(errors))
+    }
+}
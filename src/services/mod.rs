@@ -1,2 +1,18 @@
 pub mod accounts;
+pub mod auth;
+pub mod batches;
+pub mod holds;
+pub mod ledger;
+pub mod limits;
+pub mod multi_leg;
+pub mod notifier;
+pub mod pagination;
+pub mod retry;
+pub mod schedules;
+pub mod settlement;
+pub mod timestamps;
+pub mod transactions;
+pub mod two_factor;
+pub mod users;
+pub mod validation;
 pub mod webhooks;
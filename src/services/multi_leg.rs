@@ -0,0 +1,269 @@
+use crate::error::{AppError, FieldError};
+use crate::models::{MultiLegTransferResponse, TransferLeg};
+use crate::services::accounts::{create_webhook_event, lock_accounts};
+use crate::services::holds::HELD_AMOUNT;
+use crate::services::ledger::{self, JournalEntry, LedgerAccount, Posting};
+use sqlx::{types::Uuid, PgExecutor, Postgres, QueryBuilder, Row};
+use std::collections::{HashMap, HashSet};
+use tracing::instrument;
+
+pub const MAX_LEGS: usize = 100;
+
+/// A parsed leg: negative amounts leave `account_id`, positive ones arrive.
+#[derive(Clone, Copy)]
+pub struct Leg {
+    pub account_id: Uuid,
+    pub amount: i64,
+}
+
+/// Parses every leg, reporting all invalid fields at once. The legs must name
+/// each account once, include a source and a destination, and sum to zero.
+pub fn validate_legs(legs: &[TransferLeg]) -> Result<Vec<Leg>, AppError> {
+    if legs.len() < 2 {
+        return Err(AppError::InvalidRequest(
+            "A multi-leg transfer needs at least two legs",
+        ));
+    }
+    if legs.len() > MAX_LEGS {
+        return Err(AppError::InvalidRequest(
+            "A multi-leg transfer has at most 100 legs",
+        ));
+    }
+
+    let mut errors = Vec::new();
+    let mut parsed = Vec::with_capacity(legs.len());
+    let mut seen = HashSet::new();
+    for (index, leg) in legs.iter().enumerate() {
+        match Uuid::parse_str(&leg.account_id) {
+            Ok(account_id) if !seen.insert(account_id) => errors.push(FieldError {
+                field: format!("legs[{index}].account_id"),
+                message: "must not appear in more than one leg".to_string(),
+            }),
+            Ok(account_id) => parsed.push(Leg {
+                account_id,
+                amount: leg.amount,
+            }),
+            Err(_) => errors.push(FieldError {
+                field: format!("legs[{index}].account_id"),
+                message: "must be a UUID".to_string(),
+            }),
+        }
+        if leg.amount == 0 {
+            errors.push(FieldError {
+                field: format!("legs[{index}].amount"),
+                message: "must not be zero".to_string(),
+            });
+        } else if leg.amount == i64::MIN {
+            // Its magnitude does not fit in an i64
+            errors.push(FieldError {
+                field: format!("legs[{index}].amount"),
+                message: format!("must not be below {}", -i64::MAX),
+            });
+        }
+    }
+    if !errors.is_empty() {
+        return Err(AppError::Validation(errors));
+    }
+
+    let total: i128 = legs.iter().map(|leg| leg.amount as i128).sum();
+    if total != 0 {
+        return Err(AppError::Validation(vec![FieldError {
+            field: "legs".to_string(),
+            message: format!("amounts must sum to zero, not {total}"),
+        }]));
+    }
+    if !legs.iter().any(|leg| leg.amount < 0) || !legs.iter().any(|leg| leg.amount > 0) {
+        return Err(AppError::Validation(vec![FieldError {
+            field: "legs".to_string(),
+            message: "must include at least one source and one destination".to_string(),
+        }]));
+    }
+    if source_total(&parsed).is_none() {
+        return Err(AppError::Validation(vec![FieldError {
+            field: "legs".to_string(),
+            message: format!("total must not exceed {}", i64::MAX),
+        }]));
+    }
+    Ok(parsed)
+}
+
+/// Total leaving the source accounts, or `None` if it does not fit in an i64.
+pub fn source_total(legs: &[Leg]) -> Option<i64> {
+    legs.iter()
+        .filter(|leg| leg.amount < 0)
+        .try_fold(0i64, |total, leg| total.checked_sub(leg.amount))
+}
+
+/// The amount a source leg takes from its account.
+fn outgoing(leg: &Leg) -> Result<i64, AppError> {
+    leg.amount
+        .checked_neg()
+        .ok_or(AppError::InvalidRequest("Amount is too large"))
+}
+
+/// Moves money between every account in `legs` in one journal entry. All
+/// accounts are locked up front in ID order, so concurrent transfers over the
+/// same accounts cannot deadlock. Sources must belong to `business_id` and
+/// have the funds; every account must share one currency.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn execute_multi_leg_transfer(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    business_id: Uuid,
+    legs: &[Leg],
+    idempotency_key: &str,
+    request_id: &str,
+) -> Result<(Uuid, MultiLegTransferResponse), AppError> {
+    let account_ids: Vec<Uuid> = legs.iter().map(|leg| leg.account_id).collect();
+    lock_accounts(tx, &account_ids).await?;
+
+    let rows = sqlx::query(&format!(
+        "SELECT a.id, a.business_id, a.currency, a.balance - {HELD_AMOUNT} AS available_balance
+         FROM accounts a WHERE a.id = ANY($1)"
+    ))
+    .bind(&account_ids)
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(AppError::database("Failed to fetch accounts"))?;
+    let accounts: HashMap<Uuid, _> = rows
+        .iter()
+        .map(|row| (row.get::<Uuid, _>("id"), row))
+        .collect();
+
+    let mut currency: Option<String> = None;
+    for leg in legs {
+        let account = accounts.get(&leg.account_id);
+        let is_source = leg.amount < 0;
+        let account = match account {
+            Some(account) if !is_source || account.get::<Uuid, _>("business_id") == business_id => {
+                account
+            }
+            _ if is_source => {
+                return Err(AppError::NotFound(
+                    "Source account not found or does not belong to this business",
+                ))
+            }
+            _ => return Err(AppError::NotFound("Destination account not found")),
+        };
+
+        let account_currency: String = account.get("currency");
+        match &currency {
+            None => currency = Some(account_currency),
+            Some(currency) if *currency != account_currency => {
+                return Err(AppError::CurrencyMismatch {
+                    from_currency: currency.clone(),
+                    to_currency: account_currency,
+                });
+            }
+            Some(_) => {}
+        }
+
+        let available: i64 = account.get("available_balance");
+        if is_source && available < outgoing(leg)? {
+            return Err(AppError::InsufficientFunds {
+                available,
+                required: outgoing(leg)?,
+            });
+        }
+    }
+    let currency = currency.unwrap_or_default();
+    let amount = source_total(legs).ok_or(AppError::InvalidRequest("Amount is too large"))?;
+
+    let transaction_id: Uuid = sqlx::query(
+        "INSERT INTO transactions (business_id, amount, type, status, idempotency_key, request_id)
+         VALUES ($1, $2, 'multi_leg', 'success', $3, $4) RETURNING id",
+    )
+    .bind(business_id)
+    .bind(amount)
+    .bind(idempotency_key)
+    .bind(request_id)
+    .fetch_one(&mut **tx)
+    .await
+    .map(|row| row.get("id"))
+    .map_err(|_| AppError::Internal("Failed to create transaction record"))?;
+
+    let mut builder = QueryBuilder::<Postgres>::new(
+        "INSERT INTO transaction_legs (transaction_id, position, account_id, amount) ",
+    );
+    builder.push_values(legs.iter().enumerate(), |mut row, (position, leg)| {
+        row.push_bind(transaction_id)
+            .push_bind(position as i32)
+            .push_bind(leg.account_id)
+            .push_bind(leg.amount);
+    });
+    builder
+        .build()
+        .execute(&mut **tx)
+        .await
+        .map_err(|_| AppError::Internal("Failed to record transfer legs"))?;
+
+    ledger::post(
+        tx,
+        &JournalEntry {
+            kind: "multi_leg",
+            currency: &currency,
+            transaction_id: Some(transaction_id),
+            postings: legs
+                .iter()
+                .map(|leg| {
+                    let account = LedgerAccount::Customer(leg.account_id);
+                    Ok(if leg.amount < 0 {
+                        Posting::debit(account, outgoing(leg)?)
+                    } else {
+                        Posting::credit(account, leg.amount)
+                    })
+                })
+                .collect::<Result<_, AppError>>()?,
+        },
+    )
+    .await?;
+
+    let response = MultiLegTransferResponse {
+        transaction_id: transaction_id.to_string(),
+        amount,
+        currency,
+        status: "success".to_string(),
+        legs: legs
+            .iter()
+            .map(|leg| TransferLeg {
+                account_id: leg.account_id.to_string(),
+                amount: leg.amount,
+            })
+            .collect(),
+        cached: None,
+    };
+    create_webhook_event(
+        tx,
+        business_id,
+        "multi_leg_transfer.created",
+        &response,
+        request_id,
+    )
+    .await?;
+
+    Ok((transaction_id, response))
+}
+
+/// The legs of a multi-leg transfer, in request order.
+pub async fn fetch_legs<'e>(
+    executor: impl PgExecutor<'e>,
+    transaction_id: Uuid,
+) -> Result<Vec<TransferLeg>, AppError> {
+    let rows = sqlx::query(
+        "SELECT account_id, amount FROM transaction_legs WHERE transaction_id = $1 ORDER BY position",
+    )
+    .bind(transaction_id)
+    .fetch_all(executor)
+    .await
+    .map_err(|_| AppError::Internal("Failed to fetch transfer legs"))?;
+
+    Ok(rows
+        .iter()
+        .map(|row| TransferLeg {
+            account_id: row
+                .get::<Option<Uuid>, _>("account_id")
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+            amount: row.get("amount"),
+        })
+        .collect())
+}
@@ -0,0 +1,66 @@
+use crate::config::Config;
+use async_trait::async_trait;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Delivers out-of-band messages (password resets, ...) to users.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn send(&self, notification: &Notification) -> Result<(), String>;
+}
+
+/// Writes notifications to the log. Suitable for local development only.
+pub struct LogNotifier;
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    async fn send(&self, notification: &Notification) -> Result<(), String> {
+        tracing::info!(
+            to = %notification.to,
+            subject = %notification.subject,
+            body = %notification.body,
+            "Notification sent"
+        );
+        Ok(())
+    }
+}
+
+/// Appends notifications as JSON lines to a file.
+pub struct FileNotifier {
+    pub path: PathBuf,
+}
+
+#[async_trait]
+impl Notifier for FileNotifier {
+    async fn send(&self, notification: &Notification) -> Result<(), String> {
+        let mut line = serde_json::to_string(notification).map_err(|e| e.to_string())?;
+        line.push('\n');
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        file.write_all(line.as_bytes())
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+pub fn notifier_from_config(config: &Config) -> Arc<dyn Notifier> {
+    match &config.notifier_file {
+        Some(path) => Arc::new(FileNotifier { path: path.clone() }),
+        None => Arc::new(LogNotifier),
+    }
+}
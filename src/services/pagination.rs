@@ -0,0 +1,103 @@
+use crate::error::AppError;
+use crate::models::{ListResponse, PageParams, SortOrder};
+use chrono::{DateTime, NaiveDateTime};
+use sqlx::types::Uuid;
+
+pub const DEFAULT_PAGE_LIMIT: i64 = 20;
+pub const MAX_PAGE_LIMIT: i64 = 100;
+
+/// Position in a list ordered by `(created_at, id)`. Clients only ever see
+/// the encoded form, so the format can change without breaking them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_at: NaiveDateTime,
+    pub id: Uuid,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        hex::encode(format!(
+            "{}:{}",
+            self.created_at.and_utc().timestamp_micros(),
+            self.id
+        ))
+    }
+
+    pub fn decode(value: &str) -> Option<Self> {
+        let raw = String::from_utf8(hex::decode(value).ok()?).ok()?;
+        let (micros, id) = raw.split_once(':')?;
+        Some(Self {
+            created_at: DateTime::from_timestamp_micros(micros.parse().ok()?)?.naive_utc(),
+            id: Uuid::parse_str(id).ok()?,
+        })
+    }
+}
+
+/// Validated form of [`PageParams`].
+pub struct Page {
+    pub limit: i64,
+    pub after: Option<Cursor>,
+    pub order: SortOrder,
+}
+
+impl Page {
+    pub fn from_params(params: &PageParams) -> Result<Self, AppError> {
+        let limit = params.limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+        if !(1..=MAX_PAGE_LIMIT).contains(&limit) {
+            return Err(AppError::InvalidRequest("limit must be between 1 and 100"));
+        }
+
+        let after = match &params.starting_after {
+            Some(cursor) => Some(
+                Cursor::decode(cursor)
+                    .ok_or(AppError::InvalidRequest("Invalid starting_after cursor"))?,
+            ),
+            None => None,
+        };
+
+        Ok(Self {
+            limit,
+            after,
+            order: params.order.unwrap_or_default(),
+        })
+    }
+
+    /// SQL `ORDER BY` direction.
+    pub fn direction(&self) -> &'static str {
+        match self.order {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+
+    /// Comparison that selects rows after the cursor in this order.
+    pub fn after_operator(&self) -> &'static str {
+        match self.order {
+            SortOrder::Asc => ">",
+            SortOrder::Desc => "<",
+        }
+    }
+
+    /// Rows to fetch: one extra tells us whether another page exists.
+    pub fn fetch_limit(&self) -> i64 {
+        self.limit + 1
+    }
+
+    /// Builds the list envelope from up to `fetch_limit()` rows, each paired
+    /// with its cursor.
+    pub fn finish<T>(&self, mut rows: Vec<(Cursor, T)>) -> ListResponse<T> {
+        let has_more = rows.len() as i64 > self.limit;
+        rows.truncate(self.limit as usize);
+        let next_cursor = if has_more {
+            rows.last().map(|(cursor, _)| cursor.encode())
+        } else {
+            None
+        };
+
+        ListResponse {
+            data: rows.into_iter().map(|(_, item)| item).collect(),
+            has_more,
+            next_cursor,
+        }
+    }
+}
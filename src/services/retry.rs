@@ -0,0 +1,30 @@
+use crate::error::AppError;
+use std::future::Future;
+use std::time::Duration;
+
+/// Attempts made before a `Contention` error is returned to the caller.
+pub const MAX_ATTEMPTS: u32 = 3;
+
+/// Wait before the first retry; doubled for each one after.
+const RETRY_BACKOFF: Duration = Duration::from_millis(20);
+
+/// Runs `operation`, retrying it when the database aborted it for a deadlock
+/// or serialization failure. Each attempt must start its own database
+/// transaction, so a retry never sees the aborted one's work.
+pub async fn with_retry<T, F, Fut>(mut operation: F) -> Result<T, AppError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, AppError>>,
+{
+    let mut attempt = 1;
+    loop {
+        match operation().await {
+            Err(AppError::Contention(message)) if attempt < MAX_ATTEMPTS => {
+                tracing::warn!(attempt, message, "Retrying after database contention");
+                tokio::time::sleep(RETRY_BACKOFF * 2u32.pow(attempt - 1)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
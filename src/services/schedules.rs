@@ -0,0 +1,539 @@
+use crate::error::AppError;
+use crate::models::{
+    CreateScheduledTransferRequest, ItemError, ListResponse, Recurrence, RecurrenceInterval,
+    ScheduledTransferResponse, ScheduledTransferRun,
+};
+use crate::services::accounts::{create_webhook_event, execute_transfer};
+use crate::services::limits::check_currency_limits;
+use crate::services::pagination::{Cursor, Page};
+use crate::services::retry::with_retry;
+use crate::services::timestamps::{parse_rfc3339, to_rfc3339};
+use crate::state::AppState;
+use chrono::{Months, NaiveDateTime, TimeDelta, Utc};
+use sqlx::postgres::PgRow;
+use sqlx::{types::Uuid, Connection, PgExecutor, Postgres, QueryBuilder, Row};
+use std::time::Duration;
+use tracing::instrument;
+
+/// How often the worker looks for due occurrences.
+const WORKER_INTERVAL: Duration = Duration::from_secs(5);
+
+const SELECT_SCHEDULES: &str =
+    "SELECT id, business_id, from_account_id, to_account_id, amount, status, start_at, interval,
+            end_at, max_occurrences, occurrences, next_run_at, created_at
+     FROM scheduled_transfers ";
+
+/// A validated schedule request.
+pub struct NewSchedule {
+    pub from_account_id: Uuid,
+    pub to_account_id: Uuid,
+    pub amount: i64,
+    pub start_at: NaiveDateTime,
+    pub interval: Option<RecurrenceInterval>,
+    pub end_at: Option<NaiveDateTime>,
+    pub max_occurrences: Option<i32>,
+}
+
+pub fn validate_schedule_input(
+    payload: &CreateScheduledTransferRequest,
+) -> Result<NewSchedule, AppError> {
+    if payload.amount <= 0 {
+        return Err(AppError::InvalidRequest("Amount must be positive"));
+    }
+    let from_account_id = Uuid::parse_str(&payload.from_account_id)
+        .map_err(|_| AppError::InvalidRequest("Invalid from_account_id format"))?;
+    let to_account_id = Uuid::parse_str(&payload.to_account_id)
+        .map_err(|_| AppError::InvalidRequest("Invalid to_account_id format"))?;
+    if from_account_id == to_account_id {
+        return Err(AppError::InvalidRequest(
+            "Cannot transfer to the same account",
+        ));
+    }
+
+    let start_at = parse_rfc3339(&payload.start_at).ok_or(AppError::InvalidRequest(
+        "start_at must be an RFC 3339 timestamp",
+    ))?;
+    // A start in the past would run every missed occurrence at once
+    if start_at <= Utc::now().naive_utc() {
+        return Err(AppError::InvalidRequest("start_at must be in the future"));
+    }
+
+    let (interval, end_at, max_occurrences) = match &payload.recurrence {
+        None => (None, None, None),
+        Some(recurrence) => {
+            let end_at = match &recurrence.end_at {
+                Some(value) => Some(parse_rfc3339(value).ok_or(AppError::InvalidRequest(
+                    "recurrence.end_at must be an RFC 3339 timestamp",
+                ))?),
+                None => None,
+            };
+            if end_at.is_some_and(|end_at| end_at < start_at) {
+                return Err(AppError::InvalidRequest(
+                    "recurrence.end_at must not be before start_at",
+                ));
+            }
+            if recurrence.count.is_some_and(|count| count < 1) {
+                return Err(AppError::InvalidRequest(
+                    "recurrence.count must be at least 1",
+                ));
+            }
+            (Some(recurrence.interval), end_at, recurrence.count)
+        }
+    };
+
+    Ok(NewSchedule {
+        from_account_id,
+        to_account_id,
+        amount: payload.amount,
+        start_at,
+        interval,
+        end_at,
+        max_occurrences,
+    })
+}
+
+/// Time of occurrence `n`, counting from 1. Months are added to the start
+/// rather than the previous occurrence, so a schedule starting on the 31st
+/// returns to the 31st after shorter months.
+fn occurrence_at(
+    start_at: NaiveDateTime,
+    interval: Option<RecurrenceInterval>,
+    n: i32,
+) -> Option<NaiveDateTime> {
+    let steps = n.checked_sub(1)?;
+    match interval {
+        None => (steps == 0).then_some(start_at),
+        Some(RecurrenceInterval::Daily) => {
+            start_at.checked_add_signed(TimeDelta::try_days(steps.into())?)
+        }
+        Some(RecurrenceInterval::Weekly) => {
+            start_at.checked_add_signed(TimeDelta::try_weeks(steps.into())?)
+        }
+        Some(RecurrenceInterval::Monthly) => {
+            start_at.checked_add_months(Months::new(u32::try_from(steps).ok()?))
+        }
+    }
+}
+
+/// When the occurrence after the first `occurrences` is due, or `None` if the
+/// schedule is finished.
+fn next_run_at(
+    start_at: NaiveDateTime,
+    interval: Option<RecurrenceInterval>,
+    end_at: Option<NaiveDateTime>,
+    max_occurrences: Option<i32>,
+    occurrences: i32,
+) -> Option<NaiveDateTime> {
+    let n = occurrences + 1;
+    if max_occurrences.is_some_and(|max| n > max) {
+        return None;
+    }
+    occurrence_at(start_at, interval, n).filter(|at| end_at.is_none_or(|end_at| *at <= end_at))
+}
+
+fn schedule_from_row(row: &PgRow) -> ScheduledTransferResponse {
+    let interval: Option<String> = row.get("interval");
+    let recurrence = interval
+        .as_deref()
+        .and_then(RecurrenceInterval::parse)
+        .map(|interval| Recurrence {
+            interval,
+            end_at: row
+                .get::<Option<NaiveDateTime>, _>("end_at")
+                .map(to_rfc3339),
+            count: row.get("max_occurrences"),
+        });
+
+    ScheduledTransferResponse {
+        id: row.get::<Uuid, _>("id").to_string(),
+        from_account_id: row.get::<Uuid, _>("from_account_id").to_string(),
+        to_account_id: row.get::<Uuid, _>("to_account_id").to_string(),
+        amount: row.get("amount"),
+        status: row.get("status"),
+        start_at: to_rfc3339(row.get("start_at")),
+        recurrence,
+        next_run_at: row
+            .get::<Option<NaiveDateTime>, _>("next_run_at")
+            .map(to_rfc3339),
+        occurrences: row.get("occurrences"),
+        created_at: to_rfc3339(row.get("created_at")),
+        runs: None,
+        cached: None,
+    }
+}
+
+/// Checks the accounts a schedule will move money between. Funds are only
+/// checked when each occurrence runs.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn check_schedule_accounts(
+    state: &AppState,
+    business_id: Uuid,
+    schedule: &NewSchedule,
+) -> Result<(), AppError> {
+    let from_currency: String =
+        sqlx::query_scalar("SELECT currency FROM accounts WHERE id = $1 AND business_id = $2")
+            .bind(schedule.from_account_id)
+            .bind(business_id)
+            .fetch_optional(&state.pool)
+            .await
+            .map_err(|_| AppError::Internal("Failed to fetch source account"))?
+            .ok_or(AppError::NotFound(
+                "Source account not found or does not belong to this business",
+            ))?;
+    let to_currency: String = sqlx::query_scalar("SELECT currency FROM accounts WHERE id = $1")
+        .bind(schedule.to_account_id)
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(|_| AppError::Internal("Failed to fetch destination account"))?
+        .ok_or(AppError::NotFound("Destination account not found"))?;
+
+    if from_currency != to_currency {
+        return Err(AppError::CurrencyMismatch {
+            from_currency,
+            to_currency,
+        });
+    }
+    check_currency_limits(
+        &state.config.amount_limits,
+        "amount",
+        &from_currency,
+        schedule.amount,
+    )
+}
+
+#[instrument(skip_all, fields(%business_id))]
+pub async fn create_schedule(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    business_id: Uuid,
+    schedule: &NewSchedule,
+    idempotency_key: &str,
+) -> Result<ScheduledTransferResponse, AppError> {
+    let row = sqlx::query(
+        "INSERT INTO scheduled_transfers
+             (business_id, from_account_id, to_account_id, amount, start_at, interval, end_at,
+              max_occurrences, next_run_at, idempotency_key)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $5, $9)
+         RETURNING id, from_account_id, to_account_id, amount, status, start_at, interval, end_at,
+                   max_occurrences, occurrences, next_run_at, created_at",
+    )
+    .bind(business_id)
+    .bind(schedule.from_account_id)
+    .bind(schedule.to_account_id)
+    .bind(schedule.amount)
+    .bind(schedule.start_at)
+    .bind(schedule.interval.map(RecurrenceInterval::as_str))
+    .bind(schedule.end_at)
+    .bind(schedule.max_occurrences)
+    .bind(idempotency_key)
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to create scheduled transfer"))?;
+
+    Ok(schedule_from_row(&row))
+}
+
+#[instrument(skip_all, fields(%business_id))]
+pub async fn list_schedules(
+    state: &AppState,
+    business_id: Uuid,
+    page: &Page,
+) -> Result<ListResponse<ScheduledTransferResponse>, AppError> {
+    let mut builder = QueryBuilder::<Postgres>::new(SELECT_SCHEDULES);
+    builder.push("WHERE business_id = ").push_bind(business_id);
+    if let Some(cursor) = page.after {
+        builder
+            .push(format!(" AND (created_at, id) {} (", page.after_operator()))
+            .push_bind(cursor.created_at)
+            .push(", ")
+            .push_bind(cursor.id)
+            .push(")");
+    }
+    builder
+        .push(format!(
+            " ORDER BY created_at {dir}, id {dir} LIMIT ",
+            dir = page.direction()
+        ))
+        .push_bind(page.fetch_limit());
+
+    let rows = builder
+        .build()
+        .fetch_all(&state.pool)
+        .await
+        .map_err(|_| AppError::Internal("Failed to fetch scheduled transfers"))?;
+
+    let schedules = rows
+        .iter()
+        .map(|row| {
+            let cursor = Cursor {
+                created_at: row.get("created_at"),
+                id: row.get("id"),
+            };
+            (cursor, schedule_from_row(row))
+        })
+        .collect();
+
+    Ok(page.finish(schedules))
+}
+
+async fn fetch_runs<'e>(
+    executor: impl PgExecutor<'e>,
+    schedule_id: Uuid,
+) -> Result<Vec<ScheduledTransferRun>, AppError> {
+    let rows = sqlx::query(
+        "SELECT occurrence, scheduled_for, status, transaction_id, error_code, error_message, created_at
+         FROM scheduled_transfer_runs WHERE scheduled_transfer_id = $1 ORDER BY occurrence",
+    )
+    .bind(schedule_id)
+    .fetch_all(executor)
+    .await
+    .map_err(|_| AppError::Internal("Failed to fetch scheduled transfer runs"))?;
+
+    Ok(rows
+        .iter()
+        .map(|row| ScheduledTransferRun {
+            occurrence: row.get("occurrence"),
+            scheduled_for: to_rfc3339(row.get("scheduled_for")),
+            status: row.get("status"),
+            transaction_id: row
+                .get::<Option<Uuid>, _>("transaction_id")
+                .map(|id| id.to_string()),
+            error: row
+                .get::<Option<String>, _>("error_code")
+                .map(|code| ItemError {
+                    code,
+                    message: row.get("error_message"),
+                }),
+            created_at: to_rfc3339(row.get("created_at")),
+        })
+        .collect())
+}
+
+/// A schedule with every occurrence run so far.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn get_schedule(
+    state: &AppState,
+    business_id: Uuid,
+    schedule_id: Uuid,
+) -> Result<ScheduledTransferResponse, AppError> {
+    let row = sqlx::query(&format!(
+        "{SELECT_SCHEDULES}WHERE id = $1 AND business_id = $2"
+    ))
+    .bind(schedule_id)
+    .bind(business_id)
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|_| AppError::Internal("Failed to fetch scheduled transfer"))?
+    .ok_or(AppError::NotFound("Scheduled transfer not found"))?;
+
+    let mut schedule = schedule_from_row(&row);
+    schedule.runs = Some(fetch_runs(&state.pool, schedule_id).await?);
+    Ok(schedule)
+}
+
+/// Stops an active schedule. An occurrence already running finishes first,
+/// because it holds the schedule's row lock.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn cancel_schedule(
+    state: &AppState,
+    business_id: Uuid,
+    schedule_id: Uuid,
+) -> Result<ScheduledTransferResponse, AppError> {
+    let mut tx = state
+        .pool
+        .begin()
+        .await
+        .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+    let status: String = sqlx::query_scalar(
+        "SELECT status FROM scheduled_transfers WHERE id = $1 AND business_id = $2 FOR UPDATE",
+    )
+    .bind(schedule_id)
+    .bind(business_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to fetch scheduled transfer"))?
+    .ok_or(AppError::NotFound("Scheduled transfer not found"))?;
+    if status != "active" {
+        return Err(AppError::Conflict("Only active schedules can be cancelled"));
+    }
+
+    let row = sqlx::query(
+        "UPDATE scheduled_transfers SET status = 'cancelled', next_run_at = NULL, updated_at = NOW()
+         WHERE id = $1
+         RETURNING id, from_account_id, to_account_id, amount, status, start_at, interval, end_at,
+                   max_occurrences, occurrences, next_run_at, created_at",
+    )
+    .bind(schedule_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to update scheduled transfer"))?;
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::Internal("Failed to commit transaction"))?;
+
+    Ok(schedule_from_row(&row))
+}
+
+/// Runs the next due occurrence of one schedule, if any. The transfer, the run
+/// record and the schedule's advance commit together, and the transfer's
+/// idempotency key is derived from the occurrence, so no occurrence can run
+/// twice. Returns `false` when nothing is due.
+async fn run_next_occurrence(state: &AppState, run_id: &str) -> Result<bool, AppError> {
+    let mut tx = state
+        .pool
+        .begin()
+        .await
+        .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+    // Concurrent workers skip each other's schedules rather than wait
+    let Some(row) = sqlx::query(&format!(
+        "{SELECT_SCHEDULES}WHERE status = 'active' AND next_run_at <= NOW()
+         ORDER BY next_run_at
+         LIMIT 1
+         FOR UPDATE SKIP LOCKED"
+    ))
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to fetch due scheduled transfers"))?
+    else {
+        return Ok(false);
+    };
+
+    let schedule_id: Uuid = row.get("id");
+    let business_id: Uuid = row.get("business_id");
+    let scheduled_for: NaiveDateTime = row.get("next_run_at");
+    let occurrence = row.get::<i32, _>("occurrences") + 1;
+
+    // A failed transfer rolls back to the savepoint; the run is still recorded
+    let mut savepoint = tx
+        .begin()
+        .await
+        .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+    let outcome = execute_transfer(
+        &mut savepoint,
+        &state.config.amount_limits,
+        business_id,
+        row.get("from_account_id"),
+        row.get("to_account_id"),
+        row.get("amount"),
+        &format!("{schedule_id}/{occurrence}"),
+        run_id,
+    )
+    .await;
+    let (transaction_id, error) = match outcome {
+        Ok((transaction_id, transfer)) => {
+            savepoint
+                .commit()
+                .await
+                .map_err(|_| AppError::Internal("Failed to commit transaction"))?;
+            (Some((transaction_id, transfer)), None)
+        }
+        // Leaves the occurrence due, to be retried, rather than recording a
+        // failure the transfer would not hit again
+        Err(error @ AppError::Contention(_)) => return Err(error),
+        Err(error) => {
+            savepoint
+                .rollback()
+                .await
+                .map_err(|_| AppError::Internal("Failed to roll back transaction"))?;
+            (None, Some(error))
+        }
+    };
+
+    sqlx::query(
+        "INSERT INTO scheduled_transfer_runs
+             (scheduled_transfer_id, occurrence, scheduled_for, status, transaction_id, error_code, error_message)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)",
+    )
+    .bind(schedule_id)
+    .bind(occurrence)
+    .bind(scheduled_for)
+    .bind(if error.is_none() { "succeeded" } else { "failed" })
+    .bind(transaction_id.as_ref().map(|(id, _)| *id))
+    .bind(error.as_ref().map(AppError::code))
+    .bind(error.as_ref().map(AppError::message))
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to record scheduled transfer run"))?;
+
+    let interval: Option<String> = row.get("interval");
+    let next = next_run_at(
+        row.get("start_at"),
+        interval.as_deref().and_then(RecurrenceInterval::parse),
+        row.get("end_at"),
+        row.get("max_occurrences"),
+        occurrence,
+    );
+    let updated = sqlx::query(
+        "UPDATE scheduled_transfers
+         SET occurrences = $1, next_run_at = $2,
+             status = CASE WHEN $2::TIMESTAMP IS NULL THEN 'completed' ELSE status END,
+             updated_at = NOW()
+         WHERE id = $3
+         RETURNING id, from_account_id, to_account_id, amount, status, start_at, interval, end_at,
+                   max_occurrences, occurrences, next_run_at, created_at",
+    )
+    .bind(occurrence)
+    .bind(next)
+    .bind(schedule_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to update scheduled transfer"))?;
+
+    if error.is_some() {
+        let mut schedule = schedule_from_row(&updated);
+        schedule.runs = Some(
+            fetch_runs(&mut *tx, schedule_id)
+                .await?
+                .into_iter()
+                .filter(|run| run.occurrence == occurrence)
+                .collect(),
+        );
+        create_webhook_event(
+            &mut tx,
+            business_id,
+            "scheduled_transfer.failed",
+            &schedule,
+            run_id,
+        )
+        .await?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(AppError::database("Failed to commit transaction"))?;
+
+    if let Some((_, transfer)) = transaction_id {
+        state
+            .metrics
+            .record_money_movement("transfer", &transfer.currency, transfer.amount);
+    }
+    Ok(true)
+}
+
+/// Runs every due occurrence, one database transaction each. Returns how many
+/// ran, whether they succeeded or failed. An occurrence that keeps hitting
+/// contention stays due for the next run.
+#[instrument(skip_all)]
+pub async fn run_due_schedules(state: &AppState) -> Result<usize, AppError> {
+    // Transfers from one run share a request ID, for correlation in logs
+    let run_id = Uuid::new_v4().to_string();
+    let mut processed = 0;
+    while with_retry(|| run_next_occurrence(state, &run_id)).await? {
+        processed += 1;
+    }
+    Ok(processed)
+}
+
+pub async fn run_scheduler(state: AppState) {
+    let mut ticker = tokio::time::interval(WORKER_INTERVAL);
+    loop {
+        ticker.tick().await;
+        match run_due_schedules(&state).await {
+            Ok(0) => {}
+            Ok(count) => tracing::info!(count, "Ran scheduled transfers"),
+            Err(e) => tracing::error!(error = ?e, "Failed to run scheduled transfers"),
+        }
+    }
+}
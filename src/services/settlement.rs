@@ -0,0 +1,373 @@
+use crate::error::AppError;
+use crate::models::TransferResponse;
+use crate::services::accounts::{create_webhook_event, lock_accounts};
+use crate::services::ledger::{self, JournalEntry, LedgerAccount};
+use crate::services::retry::with_retry;
+use crate::state::AppState;
+use sqlx::{types::Uuid, Connection, Postgres, Row};
+use std::time::Duration;
+use tracing::instrument;
+
+/// How often the settlement job looks for pending transfers that are due.
+const SETTLEMENT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A transfer row locked for a status change, so it is settled or failed
+/// exactly once.
+pub struct LockedTransfer {
+    pub id: Uuid,
+    /// Business that made the transfer.
+    pub initiated_by: Uuid,
+    pub transaction_type: String,
+    pub status: String,
+    pub from_account_id: Option<Uuid>,
+    pub to_account_id: Option<Uuid>,
+    pub from_business_id: Option<Uuid>,
+    pub to_business_id: Option<Uuid>,
+    pub amount: i64,
+    pub currency: Option<String>,
+}
+
+impl LockedTransfer {
+    fn response(&self, status: &str) -> TransferResponse {
+        TransferResponse {
+            transaction_id: self.id.to_string(),
+            from_account_id: self
+                .from_account_id
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+            to_account_id: self
+                .to_account_id
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+            amount: self.amount,
+            currency: self.currency.clone().unwrap_or_default(),
+            status: status.to_string(),
+            cached: None,
+        }
+    }
+}
+
+async fn lock_transfer(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    transaction_id: Uuid,
+) -> Result<Option<LockedTransfer>, AppError> {
+    let row = sqlx::query(
+        "SELECT t.id, t.business_id, t.type, t.status, t.amount, t.from_account_id, t.to_account_id,
+                fa.business_id AS from_business_id, ta.business_id AS to_business_id,
+                COALESCE(fa.currency, ta.currency) AS currency
+         FROM transactions t
+         LEFT JOIN accounts fa ON fa.id = t.from_account_id
+         LEFT JOIN accounts ta ON ta.id = t.to_account_id
+         WHERE t.id = $1
+         FOR UPDATE OF t",
+    )
+    .bind(transaction_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to fetch transaction"))?;
+
+    Ok(row.map(|row| LockedTransfer {
+        id: row.get("id"),
+        initiated_by: row.get("business_id"),
+        transaction_type: row.get("type"),
+        status: row.get("status"),
+        from_account_id: row.get("from_account_id"),
+        to_account_id: row.get("to_account_id"),
+        from_business_id: row.get("from_business_id"),
+        to_business_id: row.get("to_business_id"),
+        amount: row.get("amount"),
+        currency: row.get("currency"),
+    }))
+}
+
+/// Locks a pending transfer for settlement by `business_id`, which must have
+/// made it.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn lock_pending_transfer(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    business_id: Uuid,
+    transaction_id: Uuid,
+) -> Result<LockedTransfer, AppError> {
+    let transfer = lock_transfer(tx, transaction_id)
+        .await?
+        .filter(|transfer| {
+            transfer.initiated_by == business_id
+                || transfer.from_business_id == Some(business_id)
+                || transfer.to_business_id == Some(business_id)
+        })
+        .ok_or(AppError::NotFound("Transaction not found"))?;
+
+    if transfer.transaction_type != "transfer" || transfer.status != "pending" {
+        return Err(AppError::Conflict(
+            "Only pending transfers can be settled or failed",
+        ));
+    }
+    if transfer.initiated_by != business_id {
+        return Err(AppError::Forbidden(
+            "Only the business that made a transfer can settle or fail it",
+        ));
+    }
+    Ok(transfer)
+}
+
+/// Appends an entry to a transaction's status history.
+#[instrument(skip_all)]
+async fn record_status_change(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    transaction_id: Uuid,
+    from_status: Option<&str>,
+    to_status: &str,
+    request_id: &str,
+) -> Result<(), AppError> {
+    sqlx::query(
+        "INSERT INTO transaction_status_changes (transaction_id, from_status, to_status, request_id)
+         VALUES ($1, $2, $3, $4)",
+    )
+    .bind(transaction_id)
+    .bind(from_status)
+    .bind(to_status)
+    .bind(request_id)
+    .execute(&mut **tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to record status change"))?;
+    Ok(())
+}
+
+/// Records a transfer as `pending`. Its amount is held on the source account
+/// until it is settled or failed.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn create_pending_transfer_record(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    business_id: Uuid,
+    from_account_id: Uuid,
+    to_account_id: Uuid,
+    amount: i64,
+    idempotency_key: &str,
+    request_id: &str,
+) -> Result<Uuid, AppError> {
+    let transaction_id = sqlx::query(
+        "INSERT INTO transactions (business_id, from_account_id, to_account_id, amount, type, status, idempotency_key, request_id) 
+         VALUES ($1, $2, $3, $4, 'transfer', 'pending', $5, $6) RETURNING id",
+    )
+    .bind(business_id)
+    .bind(from_account_id)
+    .bind(to_account_id)
+    .bind(amount)
+    .bind(idempotency_key)
+    .bind(request_id)
+    .fetch_one(&mut **tx)
+    .await
+    .map(|row| row.get::<Uuid, _>("id"))
+    .map_err(|_| AppError::Internal("Failed to create transaction record"))?;
+
+    record_status_change(tx, transaction_id, None, "pending", request_id).await?;
+    Ok(transaction_id)
+}
+
+/// Moves a locked pending transfer to `to_status`, records the change and
+/// tells both sides with a `transfer.updated` webhook.
+async fn update_status(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    transfer: &LockedTransfer,
+    to_status: &str,
+    request_id: &str,
+) -> Result<TransferResponse, AppError> {
+    sqlx::query("UPDATE transactions SET status = $1 WHERE id = $2")
+        .bind(to_status)
+        .bind(transfer.id)
+        .execute(&mut **tx)
+        .await
+        .map_err(|_| AppError::Internal("Failed to update transaction"))?;
+    record_status_change(
+        tx,
+        transfer.id,
+        Some(&transfer.status),
+        to_status,
+        request_id,
+    )
+    .await?;
+
+    let response = transfer.response(to_status);
+    create_webhook_event(
+        tx,
+        transfer.initiated_by,
+        "transfer.updated",
+        &response,
+        request_id,
+    )
+    .await?;
+    if let Some(to_business_id) = transfer
+        .to_business_id
+        .filter(|id| *id != transfer.initiated_by)
+    {
+        create_webhook_event(
+            tx,
+            to_business_id,
+            "transfer.updated",
+            &response,
+            request_id,
+        )
+        .await?;
+    }
+    Ok(response)
+}
+
+/// Settles a locked pending transfer: the held funds move to the destination
+/// account through the ledger.
+#[instrument(skip_all, fields(transaction_id = %transfer.id))]
+pub async fn settle_transfer(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    transfer: &LockedTransfer,
+    request_id: &str,
+) -> Result<TransferResponse, AppError> {
+    let (Some(from_account_id), Some(to_account_id), Some(currency)) = (
+        transfer.from_account_id,
+        transfer.to_account_id,
+        transfer.currency.as_deref(),
+    ) else {
+        return Err(AppError::Conflict("Transfer account no longer exists"));
+    };
+
+    lock_accounts(tx, &[from_account_id, to_account_id]).await?;
+
+    // The funds were held when the transfer was created, so they are still
+    // covered by the balance
+    ledger::post(
+        tx,
+        &JournalEntry::movement(
+            "transfer",
+            currency,
+            Some(transfer.id),
+            LedgerAccount::Customer(from_account_id),
+            LedgerAccount::Customer(to_account_id),
+            transfer.amount,
+        ),
+    )
+    .await?;
+
+    update_status(tx, transfer, "settled", request_id).await
+}
+
+/// Fails a locked pending transfer, releasing its held funds.
+#[instrument(skip_all, fields(transaction_id = %transfer.id))]
+pub async fn fail_transfer(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    transfer: &LockedTransfer,
+    request_id: &str,
+) -> Result<TransferResponse, AppError> {
+    update_status(tx, transfer, "failed", request_id).await
+}
+
+/// Settles a due transfer inside a savepoint. A transfer that cannot settle,
+/// such as one whose credit would overflow the destination balance, is rolled
+/// back and `None` is returned so the caller fails it; otherwise it would be
+/// picked first, and fail, on every run. Contention is returned as an error, so
+/// the transfer stays due.
+async fn try_settle(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    transfer: &LockedTransfer,
+    run_id: &str,
+) -> Result<Option<TransferResponse>, AppError> {
+    let mut savepoint = tx
+        .begin()
+        .await
+        .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+    match settle_transfer(&mut savepoint, transfer, run_id).await {
+        Ok(response) => {
+            savepoint
+                .commit()
+                .await
+                .map_err(AppError::database("Failed to commit transaction"))?;
+            Ok(Some(response))
+        }
+        Err(error @ AppError::Contention(_)) => Err(error),
+        Err(error) => {
+            savepoint
+                .rollback()
+                .await
+                .map_err(|_| AppError::Internal("Failed to roll back transaction"))?;
+            tracing::warn!(transaction_id = %transfer.id, %error, "Pending transfer cannot settle; failing it");
+            Ok(None)
+        }
+    }
+}
+
+/// Settles, or fails, the oldest pending transfer created at least `delay`
+/// ago in its own database transaction. Returns `false` when none is due.
+async fn settle_next_transfer(
+    state: &AppState,
+    delay: Duration,
+    run_id: &str,
+) -> Result<bool, AppError> {
+    let mut tx = state
+        .pool
+        .begin()
+        .await
+        .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+    // Concurrent runs skip each other's transfers rather than wait
+    let due: Option<Uuid> = sqlx::query_scalar(
+        "SELECT id FROM transactions
+         WHERE status = 'pending' AND created_at <= NOW() - make_interval(secs => $1)
+         ORDER BY created_at, id
+         LIMIT 1
+         FOR UPDATE SKIP LOCKED",
+    )
+    .bind(delay.as_secs_f64())
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to fetch pending transfers"))?;
+    let Some(transaction_id) = due else {
+        return Ok(false);
+    };
+    let transfer = lock_transfer(&mut tx, transaction_id)
+        .await?
+        .ok_or(AppError::Internal("Failed to fetch transaction"))?;
+
+    let settled = match (transfer.from_account_id, transfer.to_account_id) {
+        (Some(_), Some(_)) => try_settle(&mut tx, &transfer, run_id).await?,
+        _ => None,
+    };
+    if settled.is_none() {
+        fail_transfer(&mut tx, &transfer, run_id).await?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(AppError::database("Failed to commit transaction"))?;
+
+    if let Some(response) = settled {
+        state
+            .metrics
+            .record_money_movement("transfer", &response.currency, response.amount);
+    }
+    Ok(true)
+}
+
+/// Settles every pending transfer created at least `delay` ago, one database
+/// transaction each. A transfer that cannot settle, for example because its
+/// account has since been removed, is failed instead. Returns how many
+/// transfers were settled or failed. A transfer that keeps hitting contention
+/// stays pending for the next run.
+#[instrument(skip_all)]
+pub async fn settle_due_transfers(state: &AppState, delay: Duration) -> Result<usize, AppError> {
+    // Changes from one run share a request ID, for correlation in logs
+    let run_id = Uuid::new_v4().to_string();
+    let mut processed = 0;
+    while with_retry(|| settle_next_transfer(state, delay, &run_id)).await? {
+        processed += 1;
+    }
+    Ok(processed)
+}
+
+pub async fn run_settlement(state: AppState, delay: Duration) {
+    let mut ticker = tokio::time::interval(SETTLEMENT_INTERVAL);
+    loop {
+        ticker.tick().await;
+        match settle_due_transfers(&state, delay).await {
+            Ok(0) => {}
+            Ok(count) => tracing::info!(count, "Settled pending transfers"),
+            Err(e) => tracing::error!(error = ?e, "Failed to settle pending transfers"),
+        }
+    }
+}
@@ -0,0 +1,15 @@
+use chrono::{DateTime, NaiveDateTime, SecondsFormat};
+
+/// Database timestamps are stored without a zone and are always UTC.
+pub fn to_rfc3339(timestamp: NaiveDateTime) -> String {
+    timestamp
+        .and_utc()
+        .to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+/// Parses an RFC 3339 timestamp from a client into database (UTC) time.
+pub fn parse_rfc3339(value: &str) -> Option<NaiveDateTime> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|timestamp| timestamp.naive_utc())
+}
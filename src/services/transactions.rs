@@ -0,0 +1,379 @@
+use crate::error::AppError;
+use crate::models::{
+    IdempotencyKeyResponse, IdempotencyStatus, ListResponse, TransactionListQuery,
+    TransactionResponse, TransactionStatusChange,
+};
+use crate::services::accounts::lock_accounts;
+use crate::services::holds::HELD_AMOUNT;
+use crate::services::multi_leg::fetch_legs;
+use crate::services::pagination::{Cursor, Page};
+use crate::services::timestamps::{parse_rfc3339, to_rfc3339};
+use crate::state::AppState;
+use chrono::NaiveDateTime;
+use sqlx::postgres::PgRow;
+use sqlx::{types::Uuid, Postgres, QueryBuilder, Row};
+use tracing::instrument;
+
+/// Columns shared by list and retrieval. `fa`/`ta` are the source and
+/// destination accounts, joined so either side can grant visibility. A
+/// multi-leg transfer has neither; its currency comes from its journal entry.
+const SELECT_TRANSACTIONS: &str = "SELECT t.id, t.business_id, t.type, t.status, t.amount, t.from_account_id, t.to_account_id, t.idempotency_key, t.parent_transaction_id, t.created_at,
+            COALESCE(fa.currency, ta.currency, (SELECT j.currency FROM journal_entries j WHERE j.transaction_id = t.id)) AS currency 
+     FROM transactions t 
+     LEFT JOIN accounts fa ON fa.id = t.from_account_id 
+     LEFT JOIN accounts ta ON ta.id = t.to_account_id ";
+
+/// A business sees transactions it initiated and those touching its accounts,
+/// such as incoming transfers from other businesses or a leg of a multi-leg
+/// transfer.
+fn push_visible_to(builder: &mut QueryBuilder<'_, Postgres>, business_id: Uuid) {
+    builder
+        .push("WHERE (t.business_id = ")
+        .push_bind(business_id)
+        .push(" OR fa.business_id = ")
+        .push_bind(business_id)
+        .push(" OR ta.business_id = ")
+        .push_bind(business_id)
+        .push(
+            " OR EXISTS (SELECT 1 FROM transaction_legs l JOIN accounts la ON la.id = l.account_id
+                        WHERE l.transaction_id = t.id AND la.business_id = ",
+        )
+        .push_bind(business_id)
+        .push("))");
+}
+
+fn transaction_from_row(row: &PgRow, business_id: Uuid) -> TransactionResponse {
+    let initiated_by: Uuid = row.get("business_id");
+    TransactionResponse {
+        id: row.get::<Uuid, _>("id").to_string(),
+        transaction_type: row.get("type"),
+        status: row.get("status"),
+        amount: row.get("amount"),
+        currency: row.get("currency"),
+        from_account_id: row
+            .get::<Option<Uuid>, _>("from_account_id")
+            .map(|id| id.to_string()),
+        to_account_id: row
+            .get::<Option<Uuid>, _>("to_account_id")
+            .map(|id| id.to_string()),
+        idempotency_key: if initiated_by == business_id {
+            row.get("idempotency_key")
+        } else {
+            None
+        },
+        parent_transaction_id: row
+            .get::<Option<Uuid>, _>("parent_transaction_id")
+            .map(|id| id.to_string()),
+        created_at: to_rfc3339(row.get("created_at")),
+        legs: None,
+    }
+}
+
+#[instrument(skip_all, fields(%business_id))]
+pub async fn list_transactions(
+    state: &AppState,
+    business_id: Uuid,
+    filters: &TransactionListQuery,
+    page: &Page,
+) -> Result<ListResponse<TransactionResponse>, AppError> {
+    let mut builder = QueryBuilder::new(SELECT_TRANSACTIONS);
+    push_visible_to(&mut builder, business_id);
+
+    if let Some(account_id) = &filters.account_id {
+        let account_id = Uuid::parse_str(account_id)
+            .map_err(|_| AppError::InvalidRequest("Invalid account_id format"))?;
+        builder
+            .push(" AND (t.from_account_id = ")
+            .push_bind(account_id)
+            .push(" OR t.to_account_id = ")
+            .push_bind(account_id)
+            .push(" OR EXISTS (SELECT 1 FROM transaction_legs l WHERE l.transaction_id = t.id AND l.account_id = ")
+            .push_bind(account_id)
+            .push("))");
+    }
+    if let Some(transaction_type) = &filters.transaction_type {
+        builder.push(" AND t.type = ").push_bind(transaction_type);
+    }
+    if let Some(status) = &filters.status {
+        builder.push(" AND t.status = ").push_bind(status);
+    }
+    if let Some(created_after) = &filters.created_after {
+        let created_after = parse_rfc3339(created_after).ok_or(AppError::InvalidRequest(
+            "created_after must be an RFC 3339 timestamp",
+        ))?;
+        builder
+            .push(" AND t.created_at >= ")
+            .push_bind(created_after);
+    }
+    if let Some(created_before) = &filters.created_before {
+        let created_before = parse_rfc3339(created_before).ok_or(AppError::InvalidRequest(
+            "created_before must be an RFC 3339 timestamp",
+        ))?;
+        builder
+            .push(" AND t.created_at < ")
+            .push_bind(created_before);
+    }
+    if let Some(min_amount) = filters.min_amount {
+        builder.push(" AND t.amount >= ").push_bind(min_amount);
+    }
+    if let Some(max_amount) = filters.max_amount {
+        builder.push(" AND t.amount <= ").push_bind(max_amount);
+    }
+    if let Some(parent_transaction_id) = &filters.parent_transaction_id {
+        let parent_transaction_id = Uuid::parse_str(parent_transaction_id)
+            .map_err(|_| AppError::InvalidRequest("Invalid parent_transaction_id format"))?;
+        builder
+            .push(" AND t.parent_transaction_id = ")
+            .push_bind(parent_transaction_id);
+    }
+
+    if let Some(cursor) = page.after {
+        builder
+            .push(format!(
+                " AND (t.created_at, t.id) {} (",
+                page.after_operator()
+            ))
+            .push_bind(cursor.created_at)
+            .push(", ")
+            .push_bind(cursor.id)
+            .push(")");
+    }
+
+    builder
+        .push(format!(
+            " ORDER BY t.created_at {dir}, t.id {dir} LIMIT ",
+            dir = page.direction()
+        ))
+        .push_bind(page.fetch_limit());
+
+    let rows = builder
+        .build()
+        .fetch_all(&state.pool)
+        .await
+        .map_err(|_| AppError::Internal("Failed to fetch transactions"))?;
+
+    let transactions = rows
+        .iter()
+        .map(|row| {
+            let cursor = Cursor {
+                created_at: row.get("created_at"),
+                id: row.get("id"),
+            };
+            (cursor, transaction_from_row(row, business_id))
+        })
+        .collect();
+
+    Ok(page.finish(transactions))
+}
+
+#[instrument(skip_all, fields(%business_id))]
+pub async fn get_transaction(
+    state: &AppState,
+    business_id: Uuid,
+    transaction_id: Uuid,
+) -> Result<TransactionResponse, AppError> {
+    let mut builder = QueryBuilder::new(SELECT_TRANSACTIONS);
+    push_visible_to(&mut builder, business_id);
+    builder.push(" AND t.id = ").push_bind(transaction_id);
+
+    let row = builder
+        .build()
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(|_| AppError::Internal("Failed to fetch transaction"))?
+        .ok_or(AppError::NotFound("Transaction not found"))?;
+
+    let mut transaction = transaction_from_row(&row, business_id);
+    if transaction.transaction_type == "multi_leg" {
+        transaction.legs = Some(fetch_legs(&state.pool, transaction_id).await?);
+    }
+    Ok(transaction)
+}
+
+/// Status history of a transaction visible to `business_id`, oldest first.
+/// Only pending transfers record one; it is empty for other transactions.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn list_status_changes(
+    state: &AppState,
+    business_id: Uuid,
+    transaction_id: Uuid,
+) -> Result<Vec<TransactionStatusChange>, AppError> {
+    get_transaction(state, business_id, transaction_id).await?;
+
+    let rows = sqlx::query(
+        "SELECT from_status, to_status, request_id, created_at FROM transaction_status_changes
+         WHERE transaction_id = $1
+         ORDER BY created_at, id",
+    )
+    .bind(transaction_id)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(|_| AppError::Internal("Failed to fetch status history"))?;
+
+    Ok(rows
+        .iter()
+        .map(|row| TransactionStatusChange {
+            from_status: row.get("from_status"),
+            to_status: row.get("to_status"),
+            request_id: row.get("request_id"),
+            created_at: to_rfc3339(row.get("created_at")),
+        })
+        .collect())
+}
+
+/// Reports what became of a request sent with `idempotency_key`, for clients
+/// that lost the response. Read-only: nothing is reserved or re-executed.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn get_idempotency_key(
+    state: &AppState,
+    business_id: Uuid,
+    idempotency_key: &str,
+) -> Result<IdempotencyKeyResponse, AppError> {
+    let row = sqlx::query(
+        "SELECT key, status, response_body, created_at FROM idempotency_keys WHERE business_id = $1 AND key = $2",
+    )
+    .bind(business_id)
+    .bind(idempotency_key)
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|_| AppError::Internal("Failed to fetch idempotency key"))?
+    .ok_or(AppError::NotFound("Idempotency key not found"))?;
+
+    let status: IdempotencyStatus = row.get("status");
+    Ok(IdempotencyKeyResponse {
+        idempotency_key: row.get("key"),
+        status,
+        response: if status == IdempotencyStatus::Success {
+            row.get("response_body")
+        } else {
+            None
+        },
+        created_at: row
+            .get::<Option<NaiveDateTime>, _>("created_at")
+            .map(to_rfc3339),
+    })
+}
+
+/// A committed transfer, locked so concurrent reversals of it run one at a time.
+pub struct ReversibleTransfer {
+    /// Business that made the original transfer.
+    pub initiated_by: Uuid,
+    pub from_account_id: Uuid,
+    pub to_account_id: Uuid,
+    pub currency: String,
+    /// Original amount minus everything already reversed.
+    pub remaining: i64,
+    /// Available balance of the account paying the funds back.
+    pub available: i64,
+}
+
+/// Locks `transaction_id` for reversal by `business_id`, which must own the
+/// account that received the funds: a reversal takes money out of it.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn lock_reversible_transfer(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    business_id: Uuid,
+    transaction_id: Uuid,
+) -> Result<ReversibleTransfer, AppError> {
+    let row = sqlx::query(
+        "SELECT t.business_id, t.type, t.status, t.amount, t.from_account_id, t.to_account_id,
+                fa.business_id AS from_business_id, ta.business_id AS to_business_id, ta.currency
+         FROM transactions t
+         LEFT JOIN accounts fa ON fa.id = t.from_account_id
+         LEFT JOIN accounts ta ON ta.id = t.to_account_id
+         WHERE t.id = $1
+         FOR UPDATE OF t",
+    )
+    .bind(transaction_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to fetch transaction"))?
+    .ok_or(AppError::NotFound("Transaction not found"))?;
+
+    let initiated_by: Uuid = row.get("business_id");
+    let from_business_id: Option<Uuid> = row.get("from_business_id");
+    let to_business_id: Option<Uuid> = row.get("to_business_id");
+    if initiated_by != business_id
+        && from_business_id != Some(business_id)
+        && to_business_id != Some(business_id)
+    {
+        return Err(AppError::NotFound("Transaction not found"));
+    }
+
+    let transaction_type: String = row.get("type");
+    let status: String = row.get("status");
+    if transaction_type != "transfer" {
+        return Err(AppError::Conflict("Only transfers can be reversed"));
+    }
+    if status != "success" && status != "settled" {
+        return Err(AppError::Conflict(
+            "Only completed transfers can be reversed",
+        ));
+    }
+    if to_business_id != Some(business_id) {
+        return Err(AppError::Forbidden(
+            "Only the business that received a transfer can reverse it",
+        ));
+    }
+    let (Some(from_account_id), Some(to_account_id)) = (
+        row.get::<Option<Uuid>, _>("from_account_id"),
+        row.get::<Option<Uuid>, _>("to_account_id"),
+    ) else {
+        return Err(AppError::Conflict("Transfer account no longer exists"));
+    };
+
+    let reversed: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(amount), 0)::BIGINT FROM transactions
+         WHERE parent_transaction_id = $1 AND status = 'success'",
+    )
+    .bind(transaction_id)
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to fetch reversals"))?;
+
+    lock_accounts(tx, &[from_account_id, to_account_id]).await?;
+    let available: i64 = sqlx::query_scalar(&format!(
+        "SELECT a.balance - {HELD_AMOUNT} FROM accounts a WHERE a.id = $1"
+    ))
+    .bind(to_account_id)
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(AppError::database("Failed to fetch account"))?;
+
+    Ok(ReversibleTransfer {
+        initiated_by,
+        from_account_id,
+        to_account_id,
+        currency: row.get("currency"),
+        remaining: row.get::<i64, _>("amount") - reversed,
+        available,
+    })
+}
+
+/// Records a reversal paying `amount` of `transfer` back to its source account.
+#[instrument(skip_all, fields(%business_id))]
+pub async fn create_reversal_record(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    business_id: Uuid,
+    parent_transaction_id: Uuid,
+    transfer: &ReversibleTransfer,
+    amount: i64,
+    idempotency_key: &str,
+    request_id: &str,
+) -> Result<Uuid, AppError> {
+    sqlx::query(
+        "INSERT INTO transactions (business_id, from_account_id, to_account_id, amount, type, status, idempotency_key, request_id, parent_transaction_id) 
+         VALUES ($1, $2, $3, $4, 'reversal', 'success', $5, $6, $7) RETURNING id",
+    )
+    .bind(business_id)
+    .bind(transfer.to_account_id)
+    .bind(transfer.from_account_id)
+    .bind(amount)
+    .bind(idempotency_key)
+    .bind(request_id)
+    .bind(parent_transaction_id)
+    .fetch_one(&mut **tx)
+    .await
+    .map(|row| row.get::<Uuid, _>("id"))
+    .map_err(|_| AppError::Internal("Failed to create transaction record"))
+}
@@ -0,0 +1,286 @@
+use crate::error::{AppError, FieldError};
+use crate::models::{RecoveryCodesResponse, TotpEnrollmentResponse};
+use crate::services::auth::hash_token;
+use crate::state::AppState;
+use hex;
+use rand::Rng;
+use serde::Deserialize;
+use sqlx::{types::Uuid, PgExecutor, Row};
+use std::time::{SystemTime, UNIX_EPOCH};
+use totp_rs::{Algorithm, Secret, TOTP};
+use tracing::instrument;
+
+const TOTP_ISSUER: &str = "Dodo";
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP_SECS: u64 = 30;
+const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Second factor supplied alongside email/password. Flattened into login
+/// request bodies.
+#[derive(Deserialize, Default)]
+pub struct SecondFactor {
+    #[serde(default)]
+    pub totp_code: Option<String>,
+    #[serde(default)]
+    pub recovery_code: Option<String>,
+}
+
+fn build_totp(secret: &str, account_name: &str) -> Result<TOTP, AppError> {
+    let secret = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|_| AppError::Internal("Invalid two-factor secret"))?;
+
+    TOTP::new(
+        Algorithm::SHA1,
+        TOTP_DIGITS,
+        0,
+        TOTP_STEP_SECS,
+        secret,
+        Some(TOTP_ISSUER.to_string()),
+        account_name.to_string(),
+    )
+    .map_err(|_| AppError::Internal("Invalid two-factor secret"))
+}
+
+/// Returns the time step `code` belongs to, allowing one step of clock drift
+/// either way.
+fn matching_step(totp: &TOTP, code: &str) -> Option<i64> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let current = now / TOTP_STEP_SECS;
+
+    [current - 1, current, current + 1]
+        .into_iter()
+        .find(|step| totp.check(code, step * TOTP_STEP_SECS))
+        .map(|step| step as i64)
+}
+
+/// A wrong code while already signed in is a bad field, not a failed login.
+fn invalid_code() -> AppError {
+    AppError::Validation(vec![FieldError {
+        field: "totp_code".to_string(),
+        message: "is invalid".to_string(),
+    }])
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.trim().replace('-', "").to_lowercase()
+}
+
+fn generate_recovery_code() -> String {
+    let code = hex::encode(rand::thread_rng().gen::<[u8; 5]>());
+    format!("{}-{}", &code[..5], &code[5..])
+}
+
+/// Accepts a TOTP code once per time step, so an observed code cannot be
+/// replayed within its validity window.
+async fn consume_totp_code<'e>(
+    executor: impl PgExecutor<'e>,
+    user_id: Uuid,
+    secret: &str,
+    email: &str,
+    code: &str,
+) -> Result<bool, AppError> {
+    let totp = build_totp(secret, email)?;
+    let step = match matching_step(&totp, code) {
+        Some(step) => step,
+        None => return Ok(false),
+    };
+
+    let result = sqlx::query(
+        "UPDATE users SET totp_last_step = $1 
+         WHERE id = $2 AND (totp_last_step IS NULL OR totp_last_step < $1)",
+    )
+    .bind(step)
+    .bind(user_id)
+    .execute(executor)
+    .await
+    .map_err(|_| AppError::Internal("Database error"))?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+async fn consume_recovery_code<'e>(
+    executor: impl PgExecutor<'e>,
+    user_id: Uuid,
+    code: &str,
+) -> Result<bool, AppError> {
+    let result = sqlx::query(
+        "UPDATE totp_recovery_codes SET used_at = NOW() 
+         WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL",
+    )
+    .bind(user_id)
+    .bind(hash_token(&normalize_recovery_code(code)))
+    .execute(executor)
+    .await
+    .map_err(|_| AppError::Internal("Database error"))?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Checks the second factor for a user with two-factor enabled. Returns
+/// `Ok(false)` for a wrong code and an error if none was supplied.
+#[instrument(skip_all, fields(%user_id))]
+pub async fn verify_second_factor<'e>(
+    executor: impl PgExecutor<'e>,
+    user_id: Uuid,
+    secret: &str,
+    email: &str,
+    factor: &SecondFactor,
+) -> Result<bool, AppError> {
+    match (&factor.totp_code, &factor.recovery_code) {
+        (Some(code), _) => consume_totp_code(executor, user_id, secret, email, code).await,
+        (None, Some(code)) => consume_recovery_code(executor, user_id, code).await,
+        (None, None) => Err(AppError::TwoFactorRequired),
+    }
+}
+
+async fn fetch_totp_state(
+    state: &AppState,
+    user_id: Uuid,
+) -> Result<(String, Option<String>, bool), AppError> {
+    let row = sqlx::query("SELECT email, totp_secret, totp_enabled FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(|_| AppError::Internal("Database error"))?;
+
+    match row {
+        Some(row) => Ok((
+            row.get("email"),
+            row.get("totp_secret"),
+            row.get("totp_enabled"),
+        )),
+        None => Err(AppError::NotFound("User not found")),
+    }
+}
+
+/// Generates a new secret for the user. Two-factor stays off until the first
+/// code is confirmed.
+#[instrument(skip_all, fields(%user_id))]
+pub async fn begin_enrollment(
+    state: &AppState,
+    user_id: Uuid,
+) -> Result<TotpEnrollmentResponse, AppError> {
+    let (email, _, enabled) = fetch_totp_state(state, user_id).await?;
+    if enabled {
+        return Err(AppError::Conflict(
+            "Two-factor authentication already enabled",
+        ));
+    }
+
+    let secret = Secret::generate_secret().to_encoded().to_string();
+    let totp = build_totp(&secret, &email)?;
+
+    sqlx::query("UPDATE users SET totp_secret = $1, totp_last_step = NULL WHERE id = $2")
+        .bind(&secret)
+        .bind(user_id)
+        .execute(&state.pool)
+        .await
+        .map_err(|_| AppError::Internal("Failed to start two-factor enrollment"))?;
+
+    Ok(TotpEnrollmentResponse {
+        otpauth_url: totp.get_url(),
+        secret,
+    })
+}
+
+/// Enables two-factor once the user proves their authenticator works, and
+/// issues a fresh set of recovery codes.
+#[instrument(skip_all, fields(%user_id))]
+pub async fn confirm_enrollment(
+    state: &AppState,
+    user_id: Uuid,
+    code: &str,
+) -> Result<RecoveryCodesResponse, AppError> {
+    let (email, secret, enabled) = fetch_totp_state(state, user_id).await?;
+    if enabled {
+        return Err(AppError::Conflict(
+            "Two-factor authentication already enabled",
+        ));
+    }
+    let secret = match secret {
+        Some(secret) => secret,
+        None => return Err(AppError::Conflict("Two-factor enrollment not started")),
+    };
+
+    if !consume_totp_code(&state.pool, user_id, &secret, &email, code).await? {
+        return Err(invalid_code());
+    }
+
+    let recovery_codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| generate_recovery_code())
+        .collect();
+
+    let mut tx = state
+        .pool
+        .begin()
+        .await
+        .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+    sqlx::query("UPDATE users SET totp_enabled = true WHERE id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| AppError::Internal("Failed to enable two-factor"))?;
+
+    sqlx::query("DELETE FROM totp_recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| AppError::Internal("Failed to store recovery codes"))?;
+
+    for code in &recovery_codes {
+        sqlx::query("INSERT INTO totp_recovery_codes (user_id, code_hash) VALUES ($1, $2)")
+            .bind(user_id)
+            .bind(hash_token(&normalize_recovery_code(code)))
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| AppError::Internal("Failed to store recovery codes"))?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::Internal("Failed to commit transaction"))?;
+
+    Ok(RecoveryCodesResponse { recovery_codes })
+}
+
+/// Turns two-factor off. Requires a current TOTP code.
+#[instrument(skip_all, fields(%user_id))]
+pub async fn disable(state: &AppState, user_id: Uuid, code: &str) -> Result<(), AppError> {
+    let (email, secret, enabled) = fetch_totp_state(state, user_id).await?;
+    let secret = match (enabled, secret) {
+        (true, Some(secret)) => secret,
+        _ => return Err(AppError::Conflict("Two-factor authentication not enabled")),
+    };
+
+    if !consume_totp_code(&state.pool, user_id, &secret, &email, code).await? {
+        return Err(invalid_code());
+    }
+
+    let mut tx = state
+        .pool
+        .begin()
+        .await
+        .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+    sqlx::query(
+        "UPDATE users SET totp_enabled = false, totp_secret = NULL, totp_last_step = NULL WHERE id = $1",
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| AppError::Internal("Failed to disable two-factor"))?;
+
+    sqlx::query("DELETE FROM totp_recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| AppError::Internal("Failed to disable two-factor"))?;
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::Internal("Failed to commit transaction"))?;
+
+    Ok(())
+}
@@ -0,0 +1,169 @@
+use crate::error::AppError;
+use crate::models::{
+    AcceptInvitationRequest, AuthContext, InvitationResponse, InviteUserRequest, UserResponse,
+    UserRole,
+};
+use crate::services::auth::{generate_secret, hash_password, hash_token};
+use crate::services::validation::{normalize_email, Validator};
+use crate::state::AppState;
+use sqlx::{types::Uuid, Row};
+use tracing::instrument;
+
+const INVITE_TOKEN_PREFIX: &str = "inv_";
+const INVITATION_TTL_DAYS: i32 = 7;
+
+#[instrument(skip_all)]
+pub async fn invite_user(
+    state: &AppState,
+    auth: &AuthContext,
+    payload: InviteUserRequest,
+) -> Result<InvitationResponse, AppError> {
+    let email = normalize_email(&payload.email);
+
+    let mut validator = Validator::default();
+    validator.email("email", &email);
+    if payload.role == UserRole::Owner {
+        validator.error("role", "cannot invite another owner");
+    }
+    validator.finish()?;
+
+    let existing = sqlx::query("SELECT 1 FROM users WHERE email = $1")
+        .bind(&email)
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(|_| AppError::Internal("Database error"))?;
+
+    if existing.is_some() {
+        return Err(AppError::AlreadyExists("User already exists"));
+    }
+
+    let invite_token = generate_secret(INVITE_TOKEN_PREFIX);
+
+    let row = sqlx::query(
+        "INSERT INTO user_invitations (business_id, email, role, token_hash, invited_by, expires_at) 
+         VALUES ($1, $2, $3, $4, $5, NOW() + make_interval(days => $6)) RETURNING id",
+    )
+    .bind(auth.business_id)
+    .bind(&email)
+    .bind(payload.role)
+    .bind(hash_token(&invite_token))
+    .bind(auth.user_id)
+    .bind(INVITATION_TTL_DAYS)
+    .fetch_one(&state.pool)
+    .await
+    .map_err(|_| AppError::Internal("Failed to create invitation"))?;
+
+    let id: Uuid = row.get("id");
+    Ok(InvitationResponse {
+        id: id.to_string(),
+        business_id: auth.business_id.to_string(),
+        email,
+        role: payload.role,
+        invite_token,
+    })
+}
+
+#[instrument(skip_all)]
+pub async fn accept_invitation(
+    state: &AppState,
+    payload: AcceptInvitationRequest,
+) -> Result<UserResponse, AppError> {
+    let mut validator = Validator::default();
+    validator.name("name", &payload.name);
+    validator.password("password", &payload.password);
+    validator.finish()?;
+    let name = payload.name.trim();
+
+    let password_hash = hash_password(&payload.password).await?;
+
+    let mut tx = state
+        .pool
+        .begin()
+        .await
+        .map_err(|_| AppError::Internal("Failed to start transaction"))?;
+
+    let invitation = sqlx::query(
+        "SELECT id, business_id, email, role FROM user_invitations 
+         WHERE token_hash = $1 AND accepted_at IS NULL AND expires_at > NOW() 
+         FOR UPDATE",
+    )
+    .bind(hash_token(&payload.invite_token))
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| AppError::Internal("Database error"))?;
+
+    let invitation = match invitation {
+        Some(row) => row,
+        None => return Err(AppError::InvalidToken("Invalid or expired invitation")),
+    };
+
+    let invitation_id: Uuid = invitation.get("id");
+    let business_id: Uuid = invitation.get("business_id");
+    let email: String = invitation.get("email");
+    let role: UserRole = invitation.get("role");
+
+    let result = sqlx::query(
+        "INSERT INTO users (business_id, email, password_hash, name, role) 
+         VALUES ($1, $2, $3, $4, $5) RETURNING id",
+    )
+    .bind(business_id)
+    .bind(&email)
+    .bind(&password_hash)
+    .bind(name)
+    .bind(role)
+    .fetch_one(&mut *tx)
+    .await;
+
+    let user_id: Uuid = match result {
+        Ok(row) => row.get("id"),
+        Err(sqlx::Error::Database(e)) if e.constraint().is_some() => {
+            return Err(AppError::AlreadyExists("User already exists"));
+        }
+        Err(_) => return Err(AppError::Internal("Failed to create user")),
+    };
+
+    sqlx::query("UPDATE user_invitations SET accepted_at = NOW() WHERE id = $1")
+        .bind(invitation_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| AppError::Internal("Failed to accept invitation"))?;
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::Internal("Failed to commit transaction"))?;
+
+    Ok(UserResponse {
+        id: user_id.to_string(),
+        business_id: business_id.to_string(),
+        email,
+        name: Some(name.to_string()),
+        role,
+    })
+}
+
+#[instrument(skip_all, fields(%business_id))]
+pub async fn list_users(
+    state: &AppState,
+    business_id: Uuid,
+) -> Result<Vec<UserResponse>, AppError> {
+    let rows = sqlx::query(
+        "SELECT id, business_id, email, name, role FROM users WHERE business_id = $1 ORDER BY created_at",
+    )
+    .bind(business_id)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(|_| AppError::Internal("Failed to fetch users"))?;
+
+    let users = rows
+        .into_iter()
+        .map(|row| UserResponse {
+            id: row.get::<Uuid, _>("id").to_string(),
+            business_id: row.get::<Uuid, _>("business_id").to_string(),
+            email: row.get("email"),
+            name: row.get("name"),
+            role: row.get("role"),
+        })
+        .collect();
+
+    Ok(users)
+}
@@ -0,0 +1,70 @@
+use crate::error::{AppError, FieldError};
+use email_address::EmailAddress;
+
+const MIN_PASSWORD_LENGTH: usize = 10;
+const MAX_NAME_LENGTH: usize = 200;
+
+/// Collects every failing field of a request so clients can show all
+/// problems at once instead of fixing them one round-trip at a time.
+#[derive(Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+/// Canonical form of an email, used for storage and every lookup.
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+impl Validator {
+    pub fn error(&mut self, field: &str, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            message: message.into(),
+        });
+    }
+
+    /// Expects an already normalized email.
+    pub fn email(&mut self, field: &str, email: &str) {
+        if email.is_empty() {
+            self.error(field, "is required");
+        } else if !EmailAddress::is_valid(email) {
+            self.error(field, "is not a valid email address");
+        }
+    }
+
+    pub fn password(&mut self, field: &str, password: &str) {
+        if password.chars().count() < MIN_PASSWORD_LENGTH {
+            self.error(
+                field,
+                format!("must be at least {} characters", MIN_PASSWORD_LENGTH),
+            );
+        }
+        if !password.chars().any(|c| c.is_alphabetic()) {
+            self.error(field, "must contain a letter");
+        }
+        if !password.chars().any(|c| c.is_ascii_digit()) {
+            self.error(field, "must contain a digit");
+        }
+    }
+
+    pub fn name(&mut self, field: &str, name: &str) {
+        let name = name.trim();
+        if name.is_empty() {
+            self.error(field, "is required");
+        } else if name.chars().count() > MAX_NAME_LENGTH {
+            self.error(
+                field,
+                format!("must be at most {} characters", MAX_NAME_LENGTH),
+            );
+        }
+    }
+
+    pub fn finish(self) -> Result<(), AppError> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Validation(self.errors))
+        }
+    }
+}
@@ -1,18 +1,56 @@
+use crate::error::AppError;
 use crate::models::WebhookEventStatus;
 use crate::state::AppState;
-use axum::Json;
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
+use serde_json::Value;
 use sqlx::{types::Uuid, Row};
-use std::time::Duration;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::instrument;
+use tracing::Instrument;
+
+/// Timeout for a single delivery, so a slow endpoint cannot stall the worker.
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Last time the webhook worker made progress, read by the readiness probe.
+#[derive(Default)]
+pub struct WorkerHeartbeat {
+    last_beat_unix: AtomicI64,
+}
+
+impl WorkerHeartbeat {
+    pub fn beat(&self) {
+        self.last_beat_unix.store(unix_now(), Ordering::Relaxed);
+    }
+
+    /// Time since the last beat, or `None` if the worker never started.
+    pub fn age(&self) -> Option<Duration> {
+        match self.last_beat_unix.load(Ordering::Relaxed) {
+            0 => None,
+            last => Some(Duration::from_secs((unix_now() - last).max(0) as u64)),
+        }
+    }
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
 
 pub async fn process_webhooks(state: AppState) {
-    let client = reqwest::Client::new();
+    let client = reqwest::Client::builder()
+        .timeout(DELIVERY_TIMEOUT)
+        .build()
+        .expect("Failed to build webhook HTTP client");
 
     loop {
+        state.webhook_heartbeat.beat();
+
         // Fetch pending events that are due for processing (first attempt or retries after backoff)
         let events = sqlx::query(
-            "SELECT we.id, we.event_type, we.payload, we.attempts, ep.url, ep.secret 
+            "SELECT we.id, we.event_type, we.payload, we.attempts, we.request_id, ep.url, ep.secret 
              FROM webhook_events we
              JOIN webhook_endpoints ep ON we.webhook_endpoint_id = ep.id
              WHERE we.status = 'pending'::webhook_event_status 
@@ -32,63 +70,106 @@ pub async fn process_webhooks(state: AppState) {
                 }
 
                 for row in rows {
+                    state.webhook_heartbeat.beat();
+
                     let event_id: Uuid = row.get("id");
                     let url: String = row.get("url");
                     let payload: Value = row.get("payload");
                     let secret: String = row.get("secret");
+                    let event_type: String = row.get("event_type");
                     let attempts: i32 = row.get("attempts");
+                    let request_id: Option<String> = row.get("request_id");
 
-                    // TODO: For better security, sign the payload using HMAC-SHA256 with the secret
-                    // and send the signature in a header (e.g., X-Webhook-Signature).
-                    // Sending the raw secret is essentially a shared password.
+                    let span = tracing::info_span!(
+                        "webhook_delivery",
+                        %event_id,
+                        event_type = %event_type,
+                        request_id = request_id.as_deref().unwrap_or_default(),
+                        attempt = attempts + 1,
+                    );
 
-                    let result = client
-                        .post(&url)
-                        .header("X-Webhook-Secret", secret)
-                        .json(&payload)
-                        .send()
-                        .await;
+                    async {
+                        // TODO: For better security, sign the payload using HMAC-SHA256 with the secret
+                        // and send the signature in a header (e.g., X-Webhook-Signature).
+                        // Sending the raw secret is essentially a shared password.
+
+                        let mut request = client
+                            .post(&url)
+                            .header("X-Webhook-Secret", secret)
+                            .json(&payload);
+                        if let Some(request_id) = request_id {
+                            request = request.header("X-Request-Id", request_id);
+                        }
+                        let started = Instant::now();
+                        let result = request.send().await;
+                        let elapsed = started.elapsed().as_secs_f64();
 
-                    let (new_status, _error) = match result {
-                        Ok(res) => {
-                            if res.status().is_success() {
-                                (WebhookEventStatus::Delivered, None)
-                            } else {
+                        let (new_status, error) = match result {
+                            Ok(res) => {
+                                if res.status().is_success() {
+                                    (WebhookEventStatus::Delivered, None)
+                                } else {
+                                    if attempts >= 5 {
+                                        (
+                                            WebhookEventStatus::Failed,
+                                            Some(format!("HTTP {}", res.status())),
+                                        )
+                                    } else {
+                                        (
+                                            WebhookEventStatus::Pending,
+                                            Some(format!("HTTP {} (will retry)", res.status())),
+                                        )
+                                    }
+                                }
+                            }
+                            Err(e) => {
                                 if attempts >= 5 {
-                                    (
-                                        WebhookEventStatus::Failed,
-                                        Some(format!("HTTP {}", res.status())),
-                                    )
+                                    (WebhookEventStatus::Failed, Some(e.to_string()))
                                 } else {
-                                    (
-                                        WebhookEventStatus::Pending,
-                                        Some(format!("HTTP {} (will retry)", res.status())),
-                                    )
+                                    (WebhookEventStatus::Pending, Some(e.to_string()))
                                 }
                             }
-                        }
-                        Err(e) => {
-                            if attempts >= 5 {
-                                (WebhookEventStatus::Failed, Some(e.to_string()))
-                            } else {
-                                (WebhookEventStatus::Pending, Some(e.to_string()))
+                        };
+
+                        let outcome = match new_status {
+                            WebhookEventStatus::Delivered => "delivered",
+                            WebhookEventStatus::Failed => "failed",
+                            _ => "retrying",
+                        };
+                        state
+                            .metrics
+                            .webhook_deliveries
+                            .with_label_values(&[outcome])
+                            .inc();
+                        state
+                            .metrics
+                            .webhook_delivery_duration
+                            .with_label_values(&[outcome])
+                            .observe(elapsed);
+
+                        match &error {
+                            None => tracing::info!("Webhook delivered"),
+                            Some(error) => {
+                                tracing::warn!(%error, outcome, "Webhook delivery failed")
                             }
                         }
-                    };
-
-                    let _ = sqlx::query(
-                        "UPDATE webhook_events 
-                         SET status = $1, last_attempt_at = NOW(), attempts = attempts + 1 
-                         WHERE id = $2",
-                    )
-                    .bind(new_status)
-                    .bind(event_id)
-                    .execute(&state.pool)
+
+                        let _ = sqlx::query(
+                            "UPDATE webhook_events 
+                             SET status = $1, last_attempt_at = NOW(), attempts = attempts + 1 
+                             WHERE id = $2",
+                        )
+                        .bind(new_status)
+                        .bind(event_id)
+                        .execute(&state.pool)
+                        .await;
+                    }
+                    .instrument(span)
                     .await;
                 }
             }
             Err(e) => {
-                eprintln!("Error fetching webhooks: {}", e);
+                tracing::error!(error = %e, "Error fetching webhooks");
                 tokio::time::sleep(Duration::from_secs(5)).await;
             }
         }
@@ -109,11 +190,12 @@ pub struct WebhookEndpointResponse {
     pub is_active: bool,
 }
 
+#[instrument(skip_all, fields(%business_id))]
 pub async fn register_webhook(
     state: &AppState,
     business_id: Uuid,
     payload: RegisterWebhookRequest,
-) -> Result<WebhookEndpointResponse, Json<Value>> {
+) -> Result<WebhookEndpointResponse, AppError> {
     let result = sqlx::query(
         "INSERT INTO webhook_endpoints (business_id, url, secret) VALUES ($1, $2, $3) RETURNING id, is_active"
     )
@@ -134,21 +216,22 @@ pub async fn register_webhook(
                 is_active,
             })
         }
-        Err(_) => Err(Json(json!({ "error": "Failed to register webhook" }))),
+        Err(_) => Err(AppError::Internal("Failed to register webhook")),
     }
 }
 
+#[instrument(skip_all, fields(%business_id))]
 pub async fn list_webhooks(
     state: &AppState,
     business_id: Uuid,
-) -> Result<Vec<WebhookEndpointResponse>, Json<Value>> {
+) -> Result<Vec<WebhookEndpointResponse>, AppError> {
     let rows = sqlx::query(
         "SELECT id, business_id, url, is_active FROM webhook_endpoints WHERE business_id = $1",
     )
     .bind(business_id)
     .fetch_all(&state.pool)
     .await
-    .map_err(|_| Json(json!({ "error": "Failed to fetch webhooks" })))?;
+    .map_err(|_| AppError::Internal("Failed to fetch webhooks"))?;
 
     let webhooks = rows
         .into_iter()
@@ -1,7 +1,15 @@
+use crate::config::Config;
+use crate::metrics::Metrics;
+use crate::services::notifier::Notifier;
+use crate::services::webhooks::WorkerHeartbeat;
 use sqlx::PgPool;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: PgPool,
+    pub config: Arc<Config>,
+    pub notifier: Arc<dyn Notifier>,
+    pub metrics: Arc<Metrics>,
+    pub webhook_heartbeat: Arc<WorkerHeartbeat>,
 }
-
@@ -0,0 +1,144 @@
+use crate::config::Config;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_sdk::error::OTelSdkResult;
+use opentelemetry_sdk::trace::{SdkTracerProvider, SpanData, SpanExporter};
+use opentelemetry_sdk::Resource;
+use serde_json::json;
+use std::io::Write;
+use std::time::UNIX_EPOCH;
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
+
+/// Shape of the log lines written to stdout.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LogFormat {
+    Json,
+    Pretty,
+}
+
+impl LogFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "pretty" | "text" => Some(Self::Pretty),
+            _ => None,
+        }
+    }
+}
+
+/// Where finished spans are exported, if anywhere.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TraceExporter {
+    /// OTLP over HTTP; the endpoint comes from `OTEL_EXPORTER_OTLP_ENDPOINT`.
+    Otlp,
+    /// One JSON line per span on stdout, for local debugging.
+    Stdout,
+}
+
+impl TraceExporter {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "otlp" => Some(Self::Otlp),
+            "stdout" | "console" => Some(Self::Stdout),
+            _ => None,
+        }
+    }
+}
+
+/// Flushes and shuts down the span exporter when dropped.
+pub struct TelemetryGuard {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Failed to shut down tracer provider: {}", e);
+            }
+        }
+    }
+}
+
+/// Installs the global tracing subscriber: an `EnvFilter` (from `RUST_LOG`,
+/// defaulting to `info`), a stdout formatter and, when configured, an
+/// OpenTelemetry layer. Keep the returned guard alive for the process lifetime.
+pub fn init(config: &Config) -> TelemetryGuard {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+
+    let fmt_layer = match config.log_format {
+        LogFormat::Json => fmt::layer()
+            .json()
+            .with_current_span(false)
+            .with_span_list(true)
+            .boxed(),
+        LogFormat::Pretty => fmt::layer().boxed(),
+    };
+
+    let provider = config.trace_exporter.map(|exporter| {
+        let resource = Resource::builder()
+            .with_service_name(config.service_name.clone())
+            .build();
+        let builder = SdkTracerProvider::builder().with_resource(resource);
+        match exporter {
+            TraceExporter::Otlp => {
+                let exporter = opentelemetry_otlp::SpanExporter::builder()
+                    .with_http()
+                    .build()
+                    .expect("Failed to build OTLP span exporter");
+                builder.with_batch_exporter(exporter).build()
+            }
+            TraceExporter::Stdout => builder.with_simple_exporter(StdoutExporter).build(),
+        }
+    });
+
+    let otel_layer = provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer().with_tracer(provider.tracer(config.service_name.clone()))
+    });
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt_layer)
+        .with(otel_layer)
+        .init();
+
+    TelemetryGuard { provider }
+}
+
+/// Writes each finished span as a JSON line to stdout.
+#[derive(Debug)]
+struct StdoutExporter;
+
+impl SpanExporter for StdoutExporter {
+    async fn export(&self, batch: Vec<SpanData>) -> OTelSdkResult {
+        let mut stdout = std::io::stdout().lock();
+        for span in batch {
+            let micros = |time: std::time::SystemTime| {
+                time.duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_micros() as u64)
+                    .unwrap_or_default()
+            };
+            let attributes: serde_json::Map<_, _> = span
+                .attributes
+                .iter()
+                .map(|kv| (kv.key.to_string(), json!(kv.value.to_string())))
+                .collect();
+            let events: Vec<_> = span
+                .events
+                .iter()
+                .map(|event| json!({ "name": event.name, "timestamp_us": micros(event.timestamp) }))
+                .collect();
+            let line = json!({
+                "span": span.name,
+                "trace_id": span.span_context.trace_id().to_string(),
+                "span_id": span.span_context.span_id().to_string(),
+                "parent_span_id": span.parent_span_id.to_string(),
+                "start_us": micros(span.start_time),
+                "duration_us": micros(span.end_time).saturating_sub(micros(span.start_time)),
+                "attributes": attributes,
+                "events": events,
+            });
+            let _ = writeln!(stdout, "{}", line);
+        }
+        Ok(())
+    }
+}